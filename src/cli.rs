@@ -35,6 +35,9 @@ pub fn execute_cmd(cfg: DsConfig, opts: MainOpts) -> Result<(), CmdError> {
         SubCommand::SearchSummary(input) => input.exec(&ctx)?,
         SubCommand::Source(input) => input.exec(&ctx)?,
         SubCommand::Admin(input) => input.exec(&ctx)?,
+        SubCommand::Addon(input) => input.exec(&ctx)?,
+        SubCommand::ClientSettings(input) => input.exec(&ctx)?,
+        SubCommand::Classifier(input) => input.exec(&ctx)?,
         SubCommand::FileExists(input) => input.exec(&ctx)?,
         SubCommand::GenInvite(input) => input.exec(&ctx)?,
         SubCommand::Register(input) => input.exec(&ctx)?,
@@ -44,6 +47,7 @@ pub fn execute_cmd(cfg: DsConfig, opts: MainOpts) -> Result<(), CmdError> {
         SubCommand::View(input) => input.exec(&ctx)?,
         SubCommand::Cleanup(input) => input.exec(&ctx)?,
         SubCommand::Export(input) => input.exec(&ctx)?,
+        SubCommand::Import(input) => input.exec(&ctx)?,
     };
     Ok(())
 }
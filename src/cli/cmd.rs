@@ -9,14 +9,18 @@
 //! implements for this type the `Cmd` trait. Each input type is
 //! referenced in the subcommand enum.
 
+pub mod addon;
 pub mod admin;
 pub mod bookmark;
+pub mod classifier;
 pub mod cleanup;
+pub mod client_settings;
 pub mod download;
 pub mod export;
 pub mod file_exists;
 pub mod generate_completions;
 pub mod geninvite;
+pub mod import;
 pub mod item;
 pub mod login;
 pub mod logout;
@@ -32,6 +36,7 @@ pub mod watch;
 
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use super::opts::Format;
 use super::sink::{Error as SinkError, Sink};
@@ -39,6 +44,7 @@ use crate::cli;
 use crate::cli::opts::CommonOpts;
 use crate::config::{ConfigError, DsConfig};
 use crate::http::proxy::ProxySetting;
+use crate::http::retry::RetryPolicy;
 use crate::http::{self, Client};
 use serde::Serialize;
 use snafu::{ResultExt, Snafu};
@@ -49,7 +55,7 @@ use snafu::{ResultExt, Snafu};
 pub trait Cmd {
     type CmdError;
 
-    fn exec<'a>(&self, args: &'a Context) -> Result<(), Self::CmdError>;
+    fn exec(&self, args: &Context) -> Result<(), Self::CmdError>;
 }
 
 /// An environment for running a command.
@@ -70,7 +76,8 @@ impl Context<'_> {
             &extra_certificate(opts, cfg),
             accept_invalid_certs(opts, cfg),
         )
-        .context(ContextCreateSnafu)?;
+        .context(ContextCreateSnafu)?
+        .with_retry(retry_policy(opts, cfg));
         Ok(Context { opts, cfg, client })
     }
 
@@ -107,6 +114,28 @@ fn docspell_url(opts: &CommonOpts, cfg: &DsConfig) -> String {
     }
 }
 
+fn retry_policy(opts: &CommonOpts, cfg: &DsConfig) -> RetryPolicy {
+    let default = RetryPolicy::default();
+    let policy = RetryPolicy {
+        max_attempts: opts
+            .retry_attempts
+            .or(cfg.retry_attempts)
+            .unwrap_or(default.max_attempts),
+        backoff: opts
+            .retry_backoff
+            .or(cfg.retry_backoff_millis)
+            .map(Duration::from_millis)
+            .unwrap_or(default.backoff),
+        status_codes: opts
+            .retry_status
+            .clone()
+            .or_else(|| cfg.retry_status_codes.clone())
+            .unwrap_or(default.status_codes),
+    };
+    log::debug!("Using retry policy: {:?}", policy);
+    policy
+}
+
 fn accept_invalid_certs(opts: &CommonOpts, cfg: &DsConfig) -> bool {
     opts.accept_invalid_certificates || cfg.accept_invalid_certificates.unwrap_or(false)
 }
@@ -143,15 +172,27 @@ pub enum CmdError {
     #[snafu(display("Export - {}", source))]
     Export { source: export::Error },
 
+    #[snafu(display("Import - {}", source))]
+    Import { source: import::Error },
+
     #[snafu(display("Watch - {}", source))]
     Watch { source: watch::Error },
 
     #[snafu(display("Upload - {}", source))]
     Upload { source: upload::Error },
 
+    #[snafu(display("Addon - {}", source))]
+    Addon { source: addon::Error },
+
     #[snafu(display("Admin - {}", source))]
     Admin { source: admin::Error },
 
+    #[snafu(display("Classifier - {}", source))]
+    Classifier { source: classifier::Error },
+
+    #[snafu(display("ClientSettings - {}", source))]
+    ClientSettings { source: client_settings::Error },
+
     #[snafu(display("Cleanup - {}", source))]
     Cleanup { source: cleanup::Error },
 
@@ -262,6 +303,21 @@ impl From<source::Error> for CmdError {
         CmdError::Source { source }
     }
 }
+impl From<addon::Error> for CmdError {
+    fn from(source: addon::Error) -> Self {
+        CmdError::Addon { source }
+    }
+}
+impl From<classifier::Error> for CmdError {
+    fn from(source: classifier::Error) -> Self {
+        CmdError::Classifier { source }
+    }
+}
+impl From<client_settings::Error> for CmdError {
+    fn from(source: client_settings::Error) -> Self {
+        CmdError::ClientSettings { source }
+    }
+}
 impl From<item::Error> for CmdError {
     fn from(source: item::Error) -> Self {
         CmdError::Item { source }
@@ -302,5 +358,10 @@ impl From<export::Error> for CmdError {
         CmdError::Export { source }
     }
 }
+impl From<import::Error> for CmdError {
+    fn from(source: import::Error) -> Self {
+        CmdError::Import { source }
+    }
+}
 
 const DSC_DOCSPELL_URL: &str = "DSC_DOCSPELL_URL";
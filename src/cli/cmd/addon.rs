@@ -0,0 +1,68 @@
+pub mod delete;
+pub mod install;
+pub mod list;
+pub mod run;
+pub mod run_config;
+pub mod update;
+
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+
+/// Manage addons.
+///
+/// Addons must be enabled in the configuration of the Docspell
+/// server. Addons and run configurations can be referenced by their
+/// name or (a prefix of) their id.
+#[derive(Parser, std::fmt::Debug)]
+pub struct Input {
+    #[command(subcommand)]
+    pub subcmd: AddonCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum AddonCommand {
+    #[command(version)]
+    List(list::Input),
+
+    #[command(version)]
+    Install(install::Input),
+
+    #[command(version)]
+    Update(update::Input),
+
+    #[command(version)]
+    Delete(delete::Input),
+
+    #[command(version)]
+    RunConfig(run_config::Input),
+
+    #[command(version)]
+    Run(run::Input),
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    List { source: list::Error },
+    Install { source: install::Error },
+    Update { source: update::Error },
+    Delete { source: delete::Error },
+    RunConfig { source: run_config::Error },
+    Run { source: run::Error },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        match &self.subcmd {
+            AddonCommand::List(input) => input.exec(ctx).context(ListSnafu),
+            AddonCommand::Install(input) => input.exec(ctx).context(InstallSnafu),
+            AddonCommand::Update(input) => input.exec(ctx).context(UpdateSnafu),
+            AddonCommand::Delete(input) => input.exec(ctx).context(DeleteSnafu),
+            AddonCommand::RunConfig(input) => input.exec(ctx).context(RunConfigSnafu),
+            AddonCommand::Run(input) => input.exec(ctx).context(RunSnafu),
+        }
+    }
+}
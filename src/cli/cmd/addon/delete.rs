@@ -0,0 +1,35 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Deletes an addon.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// The addon name or id (can be abbreviated to a prefix)
+    pub id: String,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let result = ctx
+            .client
+            .delete_addon(&ctx.opts.session, &self.id)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use clap::{Parser, ValueHint};
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::payload::AddonRegister;
+use crate::http::Error as HttpError;
+
+/// Installs an addon from a url.
+///
+/// By default the addon is installed in the background. Use `--sync`
+/// to wait for the installation to complete.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// Wait for the installation to complete.
+    #[arg(long)]
+    pub sync: bool,
+
+    /// The url to the addon archive.
+    #[arg(value_hint = ValueHint::Url)]
+    pub url: String,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let req = AddonRegister {
+            url: self.url.clone(),
+        };
+        let result = ctx
+            .client
+            .install_addon(&ctx.opts.session, &req, self.sync)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Runs addons on an existing item.
+///
+/// The addons are run as defined in the given run configurations.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// The item id (can be abbreviated to a prefix)
+    #[arg(long)]
+    pub id: String,
+
+    /// The run configurations to execute, given by name or id (can be
+    /// abbreviated to a prefix).
+    #[arg(required = true, num_args = 1)]
+    pub run_configs: Vec<String>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let result = ctx
+            .client
+            .run_addons(&ctx.opts.session, &self.id, &self.run_configs)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
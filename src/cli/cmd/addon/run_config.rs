@@ -0,0 +1,74 @@
+pub mod add;
+pub mod delete;
+pub mod list;
+pub mod update;
+
+use clap::{Parser, ValueEnum};
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+
+/// Manage addon run configurations.
+///
+/// A run configuration defines which addons are run with which
+/// arguments and when they are triggered.
+#[derive(Parser, std::fmt::Debug)]
+pub struct Input {
+    #[command(subcommand)]
+    pub subcmd: RunConfigCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum RunConfigCommand {
+    #[command(version)]
+    List(list::Input),
+
+    #[command(version)]
+    Add(add::Input),
+
+    #[command(version)]
+    Update(update::Input),
+
+    #[command(version)]
+    Delete(delete::Input),
+}
+
+/// When addons of a run configuration are executed.
+#[derive(ValueEnum, Debug, Clone)]
+pub enum Trigger {
+    FinalProcessItem,
+    FinalReprocessItem,
+    Scheduled,
+    ExistingItem,
+}
+impl Trigger {
+    pub fn to_value(&self) -> &'static str {
+        match self {
+            Trigger::FinalProcessItem => "final-process-item",
+            Trigger::FinalReprocessItem => "final-reprocess-item",
+            Trigger::Scheduled => "scheduled",
+            Trigger::ExistingItem => "existing-item",
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    List { source: list::Error },
+    Add { source: add::Error },
+    Update { source: update::Error },
+    Delete { source: delete::Error },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        match &self.subcmd {
+            RunConfigCommand::List(input) => input.exec(ctx).context(ListSnafu),
+            RunConfigCommand::Add(input) => input.exec(ctx).context(AddSnafu),
+            RunConfigCommand::Update(input) => input.exec(ctx).context(UpdateSnafu),
+            RunConfigCommand::Delete(input) => input.exec(ctx).context(DeleteSnafu),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use clap::{ArgAction, Parser};
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context, Trigger};
+use crate::cli::sink::Error as SinkError;
+use crate::http::payload::{AddonRef, AddonRunConfig};
+use crate::http::Error as HttpError;
+
+/// Creates a new addon run configuration.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// The name of the run configuration.
+    #[arg(long)]
+    pub name: String,
+
+    /// The addons to run, given by name or id (can be abbreviated to
+    /// a prefix). The option can be repeated multiple times.
+    #[arg(long, required = true, num_args = 1)]
+    pub addon: Vec<String>,
+
+    /// Arguments passed to every addon, usually some json.
+    #[arg(long, default_value = "")]
+    pub args: String,
+
+    /// When to run the addons. The option can be repeated multiple
+    /// times.
+    #[arg(long, value_enum, required = true, num_args = 1)]
+    pub trigger: Vec<Trigger>,
+
+    /// A calendar event when using the `scheduled` trigger, like
+    /// `*-*-* 01:00`.
+    #[arg(long)]
+    pub schedule: Option<String>,
+
+    /// Create the run configuration disabled.
+    #[arg(long = "disabled", action = ArgAction::SetFalse)]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let mut addons = Vec::new();
+        for id in &self.addon {
+            let addon = ctx
+                .client
+                .find_addon(&ctx.opts.session, id)
+                .context(HttpClientSnafu)?;
+            addons.push(AddonRef {
+                addon_id: addon.id,
+                name: addon.name,
+                version: addon.version,
+                description: addon.description,
+                args: self.args.clone(),
+            });
+        }
+        let cfg = AddonRunConfig {
+            id: "".into(),
+            name: self.name.clone(),
+            enabled: self.enabled,
+            user_id: None,
+            schedule: self.schedule.clone(),
+            trigger: self
+                .trigger
+                .iter()
+                .map(|t| t.to_value().to_string())
+                .collect(),
+            addons,
+        };
+        let result = ctx
+            .client
+            .add_addon_run_config(&ctx.opts.session, &cfg)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
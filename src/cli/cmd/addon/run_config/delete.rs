@@ -0,0 +1,39 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Deletes an addon run configuration.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// The run configuration name or id (can be abbreviated to a prefix)
+    pub id: String,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let cfg = ctx
+            .client
+            .find_addon_run_config(&ctx.opts.session, &self.id)
+            .context(HttpClientSnafu)?;
+        let result = ctx
+            .client
+            .delete_addon_run_config(&ctx.opts.session, &cfg.id)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Lists all addon run configurations.
+#[derive(Parser, Debug)]
+pub struct Input {}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let configs = ctx
+            .client
+            .list_addon_run_configs(&ctx.opts.session)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(configs).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
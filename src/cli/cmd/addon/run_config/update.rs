@@ -0,0 +1,85 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context, Trigger};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Changes an addon run configuration.
+///
+/// Only the given properties are changed, all others are kept.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// A new name for the run configuration.
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Enable or disable the run configuration.
+    #[arg(long)]
+    pub enabled: Option<bool>,
+
+    /// Replace the triggers. The option can be repeated multiple
+    /// times.
+    #[arg(long, value_enum, num_args = 1)]
+    pub trigger: Vec<Trigger>,
+
+    /// A calendar event when using the `scheduled` trigger, like
+    /// `*-*-* 01:00`.
+    #[arg(long)]
+    pub schedule: Option<String>,
+
+    /// Replace the arguments of all addons.
+    #[arg(long)]
+    pub args: Option<String>,
+
+    /// The run configuration name or id (can be abbreviated to a
+    /// prefix)
+    pub id: String,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let mut cfg = ctx
+            .client
+            .find_addon_run_config(&ctx.opts.session, &self.id)
+            .context(HttpClientSnafu)?;
+        if let Some(name) = &self.name {
+            cfg.name = name.clone();
+        }
+        if let Some(enabled) = self.enabled {
+            cfg.enabled = enabled;
+        }
+        if !self.trigger.is_empty() {
+            cfg.trigger = self
+                .trigger
+                .iter()
+                .map(|t| t.to_value().to_string())
+                .collect();
+        }
+        if self.schedule.is_some() {
+            cfg.schedule = self.schedule.clone();
+        }
+        if let Some(args) = &self.args {
+            for addon in cfg.addons.iter_mut() {
+                addon.args = args.clone();
+            }
+        }
+        let result = ctx
+            .client
+            .update_addon_run_config(&ctx.opts.session, &cfg.id, &cfg)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
@@ -111,7 +111,7 @@ fn get_secret(opts: &Input, ctx: &Context) -> Option<String> {
         .admin_secret
         .as_ref()
         .or(ctx.cfg.admin_secret.as_ref())
-        .map(String::clone);
+        .cloned();
 
     if secret.is_some() && ctx.opts.verbose > 2 {
         log::debug!("Using secret: {:?}", secret);
@@ -0,0 +1,45 @@
+pub mod learn;
+pub mod settings;
+
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+
+/// Manage the classifier of the collective.
+///
+/// The classifier is used to suggest tags for new items. It is
+/// learned periodically from the existing items, but the learning
+/// can also be started manually, for example after re-tagging many
+/// items.
+#[derive(Parser, std::fmt::Debug)]
+pub struct Input {
+    #[command(subcommand)]
+    pub subcmd: ClassifierCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum ClassifierCommand {
+    #[command(version)]
+    Learn(learn::Input),
+
+    #[command(version)]
+    Settings(settings::Input),
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    Learn { source: learn::Error },
+    Settings { source: settings::Error },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        match &self.subcmd {
+            ClassifierCommand::Learn(input) => input.exec(ctx).context(LearnSnafu),
+            ClassifierCommand::Settings(input) => input.exec(ctx).context(SettingsSnafu),
+        }
+    }
+}
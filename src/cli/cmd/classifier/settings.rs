@@ -0,0 +1,37 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Shows the classifier settings of the collective.
+///
+/// This is the schedule for learning, the number of items to learn
+/// from and the tag categories that are used (whitelist) or excluded
+/// (blacklist).
+#[derive(Parser, Debug)]
+pub struct Input {}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let settings = ctx
+            .client
+            .get_collective_settings(&ctx.opts.session)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(settings.classifier)
+            .context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
@@ -6,16 +6,20 @@ use super::{Cmd, Context};
 use crate::http::Error as HttpError;
 use crate::util::{digest, file};
 use crate::{
-    cli::opts::{EndpointOpts, FileAction, FileAuthError},
+    cli::opts::{ActionSpec, EndpointOpts, FileAction, FileAuthError},
     util::file::FileActionResult,
 };
-use crate::{cli::sink::Error as SinkError, http::payload::BasicResult};
+use crate::{
+    cli::sink::Error as SinkError,
+    http::payload::{BasicResult, CheckFileResult},
+};
 
 /// Cleans directories from files that are in Docspell.
 ///
 /// Traverses one or more directories and check each file whether it
-/// exists in Docspell. If so, it can be deleted or moved to another
-/// place.
+/// exists in Docspell. If so, it can be deleted, moved or copied to
+/// another place. The action given with `--on-duplicate` takes
+/// precedence over the others.
 ///
 /// If you want to upload all files that don't exists in some
 /// directory, use the `upload` command.
@@ -58,7 +62,7 @@ pub enum Error {
     #[snafu(display("Cannot delete or move: {}", source))]
     FileActionError { source: std::io::Error },
 
-    #[snafu(display("No action given. Use --move or --delete."))]
+    #[snafu(display("No action given. Use --move, --copy or --delete."))]
     NoAction,
 
     #[snafu(display("Cannot get credentials: {}", source))]
@@ -90,21 +94,16 @@ impl Cmd for Input {
 }
 
 fn check_args(args: &Input) -> Result<(), Error> {
-    match &args.action.move_to {
-        Some(path) => {
+    match args.action.duplicate_spec() {
+        ActionSpec::Move(path) | ActionSpec::Copy(path) => {
             if path.is_dir() {
                 Ok(())
             } else {
-                Err(Error::TargetNotDirectory { path: path.clone() })
-            }
-        }
-        None => {
-            if args.action.delete {
-                Ok(())
-            } else {
-                Err(Error::NoAction)
+                Err(Error::TargetNotDirectory { path })
             }
         }
+        ActionSpec::Delete => Ok(()),
+        ActionSpec::Keep => Err(Error::NoAction),
     }
 }
 
@@ -133,12 +132,17 @@ fn cleanup_and_report(
     ctx: &Context,
 ) -> Result<u32, Error> {
     eprintln!("Check file: {}", file.display());
-    let exists = check_file_exists(file, root, &args.endpoint, ctx)?;
+    let result = check_file_exists(file, root, &args.endpoint, ctx)?;
+    let exists = result.exists;
     log::debug!("Checking file: {} (exists: {})", file.display(), exists);
     if exists {
         eprint!(" - exists: ");
         if !args.dry_run {
-            let res = args.action.execute(file, root).context(FileActionSnafu)?;
+            let item_id = result.items.first().map(|i| i.id.as_str());
+            let res = args
+                .action
+                .execute_duplicate(file, root, item_id)
+                .context(FileActionSnafu)?;
             log::debug!("Action executed: {:?}", res);
             match res {
                 FileActionResult::Deleted(_p) => {
@@ -149,6 +153,10 @@ fn cleanup_and_report(
                     eprintln!("moved.");
                     return Ok(1);
                 }
+                FileActionResult::Copied(_p) => {
+                    eprintln!("copied.");
+                    return Ok(1);
+                }
                 FileActionResult::Nothing => {
                     log::error!("No file action defined. This should not happen, because user was able to not define it");
                     return Ok(0);
@@ -167,7 +175,7 @@ fn check_file_exists(
     root: Option<&PathBuf>,
     opts: &EndpointOpts,
     ctx: &Context,
-) -> Result<bool, Error> {
+) -> Result<CheckFileResult, Error> {
     let dirs: Vec<PathBuf> = match root {
         Some(d) => vec![d.clone()],
         None => vec![],
@@ -185,5 +193,5 @@ fn check_file_exists(
         .file_exists(hash, &fauth)
         .context(HttpClientSnafu)?;
 
-    Ok(result.exists)
+    Ok(result)
 }
@@ -0,0 +1,76 @@
+pub mod copy;
+pub mod get;
+pub mod set;
+
+use clap::{Parser, ValueEnum};
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::http::payload::ClientSettingsScope;
+
+/// Manage client settings.
+///
+/// Docspell clients (like the web ui) store their settings as JSON
+/// per user and per collective. The user settings take precedence
+/// over those of the collective. These commands allow to save the
+/// settings into a file and apply them to another user or collective.
+#[derive(Parser, std::fmt::Debug)]
+pub struct Input {
+    #[command(subcommand)]
+    pub subcmd: ClientSettingsCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum ClientSettingsCommand {
+    #[command(version)]
+    Get(get::Input),
+
+    #[command(version)]
+    Set(set::Input),
+
+    #[command(version)]
+    Copy(copy::Input),
+}
+
+/// The settings of the web ui are stored with this client id.
+pub const DEFAULT_CLIENT_ID: &str = "webClient";
+
+/// The location of client settings.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The settings of the current user.
+    User,
+    /// The settings of the collective, used for all its users.
+    Collective,
+    /// The settings of the user merged with those of the collective.
+    Merged,
+}
+
+impl Scope {
+    pub fn to_scope(self) -> ClientSettingsScope {
+        match self {
+            Scope::User => ClientSettingsScope::User,
+            Scope::Collective => ClientSettingsScope::Collective,
+            Scope::Merged => ClientSettingsScope::Merged,
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    Get { source: get::Error },
+    Set { source: set::Error },
+    Copy { source: copy::Error },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        match &self.subcmd {
+            ClientSettingsCommand::Get(input) => input.exec(ctx).context(GetSnafu),
+            ClientSettingsCommand::Set(input) => input.exec(ctx).context(SetSnafu),
+            ClientSettingsCommand::Copy(input) => input.exec(ctx).context(CopySnafu),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context, Scope, DEFAULT_CLIENT_ID};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Copies client settings.
+///
+/// Reads the settings from one location and replaces the settings at
+/// another with them. For example, this can be used to make the
+/// current user's settings the default for the whole collective.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// Where to read the settings from.
+    #[arg(long, value_enum, default_value = "user")]
+    pub from: Scope,
+
+    /// Where to write the settings to.
+    #[arg(long, value_enum)]
+    pub to: Scope,
+
+    /// The id of the client to read the settings from.
+    #[arg(long, default_value = DEFAULT_CLIENT_ID)]
+    pub client_id: String,
+
+    /// The id of the client to write the settings to. Defaults to the
+    /// value of `--client-id`.
+    #[arg(long)]
+    pub to_client_id: Option<String>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+
+    #[snafu(display("The merged settings cannot be changed, use user or collective"))]
+    MergedScope,
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        if self.to == Scope::Merged {
+            return Err(Error::MergedScope);
+        }
+        let to_client_id = self.to_client_id.as_ref().unwrap_or(&self.client_id);
+        let settings = ctx
+            .client
+            .get_client_settings(&ctx.opts.session, &self.client_id, self.from.to_scope())
+            .context(HttpClientSnafu)?;
+        let result = ctx
+            .client
+            .set_client_settings(
+                &ctx.opts.session,
+                to_client_id,
+                self.to.to_scope(),
+                &settings,
+            )
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
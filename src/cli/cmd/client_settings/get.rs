@@ -0,0 +1,43 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context, Scope, DEFAULT_CLIENT_ID};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Gets the client settings.
+///
+/// With the `json` format, the settings are printed as pretty JSON
+/// that can be stored in a file and later applied via `set`.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// Which settings to get.
+    #[arg(long, value_enum, default_value = "merged")]
+    pub scope: Scope,
+
+    /// The id of the client.
+    #[arg(long, default_value = DEFAULT_CLIENT_ID)]
+    pub client_id: String,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let settings = ctx
+            .client
+            .get_client_settings(&ctx.opts.session, &self.client_id, self.scope.to_scope())
+            .context(HttpClientSnafu)?;
+        ctx.write_result(settings).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use clap::{Parser, ValueHint};
+use snafu::{ResultExt, Snafu};
+use std::path::PathBuf;
+
+use super::{Cmd, Context, Scope, DEFAULT_CLIENT_ID};
+use crate::cli::sink::Error as SinkError;
+use crate::http::payload::ClientSettings;
+use crate::http::Error as HttpError;
+
+/// Sets the client settings from a JSON file.
+///
+/// The existing settings of the user or collective are replaced with
+/// the contents of the file.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// Which settings to replace.
+    #[arg(long, value_enum, default_value = "user")]
+    pub scope: Scope,
+
+    /// The id of the client.
+    #[arg(long, default_value = DEFAULT_CLIENT_ID)]
+    pub client_id: String,
+
+    /// The JSON file containing the settings. Use `-` to read from
+    /// stdin.
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+
+    #[snafu(display("Error reading file {}: {}", path.display(), source))]
+    ReadFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Error parsing JSON from {}: {}", path.display(), source))]
+    Json {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("The merged settings cannot be changed, use user or collective"))]
+    MergedScope,
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        if self.scope == Scope::Merged {
+            return Err(Error::MergedScope);
+        }
+        let settings = read_settings(&self.file)?;
+        let result = ctx
+            .client
+            .set_client_settings(
+                &ctx.opts.session,
+                &self.client_id,
+                self.scope.to_scope(),
+                &settings,
+            )
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
+
+fn read_settings(file: &PathBuf) -> Result<ClientSettings, Error> {
+    if file.as_os_str() == "-" {
+        serde_json::from_reader(std::io::stdin()).context(JsonSnafu { path: file })
+    } else {
+        let f = std::fs::File::open(file).context(ReadFileSnafu { path: file })?;
+        serde_json::from_reader(std::io::BufReader::new(f)).context(JsonSnafu { path: file })
+    }
+}
@@ -139,7 +139,7 @@ impl Cmd for Input {
                         .unwrap_or_else(|| PathBuf::from("docspell-files.zip"));
                     if let Some(parent) = zip_file.parent() {
                         if !parent.exists() {
-                            std::fs::create_dir_all(&parent).context(CreateFileSnafu)?;
+                            std::fs::create_dir_all(parent).context(CreateFileSnafu)?;
                         }
                     }
                     println!(
@@ -1,7 +1,21 @@
+mod archive;
+pub mod manifest;
+mod state;
+mod template;
+pub mod verify;
+
+use chrono::NaiveDate;
 use clap::{ArgGroup, Parser, ValueEnum};
 use snafu::{ResultExt, Snafu};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use self::archive::ArchiveWriter;
+use self::manifest::{Manifest, MANIFEST_FILE};
+use self::state::{Change, ExportState, FileEntry, ItemEntry, LastRun, Progress};
+use self::template::PathTemplate;
 use super::{Cmd, Context};
 use crate::cli::opts::Format;
 use crate::cli::sink::Error as SinkError;
@@ -10,16 +24,52 @@ use crate::http::payload::{Item, SearchMode, SearchReq};
 use crate::http::{Downloads, Error as HttpError};
 use crate::util::file;
 
-#[derive(ValueEnum, Clone, Copy, Debug)]
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
 pub enum LinkNaming {
     /// Name links to items after the items' id.
+    #[default]
     Id,
     // Name links to items after the items' sanitized name.
     Name,
 }
-impl Default for LinkNaming {
-    fn default() -> Self {
-        LinkNaming::Id
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum RemovedAction {
+    /// Keep the item's files, but put a `removed.txt` file in its
+    /// directory.
+    Mark,
+    /// Delete the item's directory.
+    Delete,
+}
+
+/// Marks exported items that have been removed on the server.
+pub const REMOVED_FILE: &str = "removed.txt";
+
+/// The items to export with `--since`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Since {
+    /// Items created since the last export of the query.
+    Last,
+    /// Items created on or after this date.
+    Date(NaiveDate),
+}
+
+impl FromStr for Since {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "last" {
+            Ok(Since::Last)
+        } else {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map(Since::Date)
+                .map_err(|_| {
+                    format!(
+                        "Invalid value '{}', use 'last' or a date like 2024-01-31",
+                        s
+                    )
+                })
+        }
     }
 }
 
@@ -38,14 +88,37 @@ impl Default for LinkNaming {
 /// used to create another subdirectory. Then the complete item id is
 /// used for another subdirectory. In the last one, a file
 /// `metadata.json` is created that contains all the metadata to the
-/// item (tags, correspondents, etc). The attachments are all stored
-/// in the `files` subdirectory.
+/// item (tags, correspondents, related items, etc). The attachments
+/// are all stored in the `files` subdirectory.
 ///
 /// The `--*-links` options can be used to create a symlink tree based
 /// on some metadata, like tags, correspondents or item date.
+///
+/// The export keeps track of all items in a state file in the
+/// `target` directory, containing a digest of their metadata and the
+/// checksums of their files. Items that have been exported before and
+/// didn't change are skipped. Their files are checked by size and
+/// modification time, use `--verify` to compare the checksums. This
+/// shows what changed since the last run. An interrupted export with
+/// `--all` continues at the page where it stopped, when it is run
+/// again with the same query.
+///
+/// At the end, a `manifest.json` file is written listing all files
+/// below `items` with their size and SHA-256 checksum. Use `export
+/// verify` to check the export against it.
+///
+/// With `--archive` the same structure is written into a zip or
+/// tar.gz file instead, for example to put it into cold storage.
 #[derive(Parser, std::fmt::Debug)]
-#[command(group = ArgGroup::new("kind"))]
+#[command(
+    group = ArgGroup::new("kind"),
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Input {
+    #[command(subcommand)]
+    subcmd: Option<ExportCommand>,
+
     /// Limit the number of results.
     #[arg(short, long, default_value = "100")]
     limit: u32,
@@ -64,6 +137,13 @@ pub struct Input {
     #[arg(long)]
     overwrite: bool,
 
+    /// Compare the checksums of files from a previous export instead
+    /// of only their size and modification time. This finds files
+    /// that have been changed in place, but reads every file of the
+    /// export.
+    #[arg(long)]
+    verify: bool,
+
     /// Specify after which of an items' property the links to it
     /// should be named. (Defaults to id)
     #[arg(long, value_enum)]
@@ -89,6 +169,23 @@ pub struct Input {
     #[arg(long)]
     correspondent_links: bool,
 
+    /// Create symlinks by concerning person or equipment. This may
+    /// not work on some file systems.
+    #[arg(long)]
+    concerning_links: bool,
+
+    /// Create symlinks by the value of the given custom field, below
+    /// `by_field/<fieldname>`. Can be given multiple times. This may
+    /// not work on some file systems.
+    #[arg(long, value_name = "FIELDNAME")]
+    field_links: Vec<String>,
+
+    /// Group the tag links by the tag's category, creating
+    /// `by_tag/<category>/<tag>`. Tags without a category are put
+    /// below `by_tag/none`.
+    #[arg(long, requires = "tag_links")]
+    tag_categories: bool,
+
     /// If your Folder-names contain a custom delimiter used to represent
     /// flat hierarchy (e.g. "Financial/Invoices"), the delimiter you set
     /// with this option is used to split the Folder name into a path, which
@@ -96,20 +193,83 @@ pub struct Input {
     #[arg(long)]
     folder_delimiter: Option<String>,
 
+    /// Export only the items created on or after the given date, like
+    /// `2024-01-31`, or since the `last` complete export of the same
+    /// query into the target. Without a previous export, all items
+    /// are exported. The query language can't select items by their
+    /// last update, so changes to older items are only exported by a
+    /// run without this option. To find exported items that have been
+    /// deleted or trashed on the server, the ids of all items of the
+    /// query are fetched without details. These items are handled as
+    /// defined by `--removed`. This implies `--all`.
+    #[arg(long, value_name = "DATE|last", conflicts_with_all = ["offset", "prune"])]
+    since: Option<Since>,
+
+    /// What to do with exported items that have been deleted or
+    /// trashed on the server. (Defaults to mark)
+    #[arg(long, value_enum, requires = "since")]
+    removed: Option<RemovedAction>,
+
+    /// Writes all files into a tree defined by this template instead
+    /// of the `items` directory, for example
+    /// `{year}/{correspondent}/{date}-{name}.{ext}`. Placeholders are
+    /// `id`, `name`, `date`, `year`, `month`, `day`, `correspondent`,
+    /// `concerning`, `folder`, `tags`, `direction`, `state`, `source`,
+    /// `file` (the file name without extension), `ext` (empty, with
+    /// the dot before it removed, for files without extension) and
+    /// `field:<name>` for custom fields, given by name or label. The
+    /// paths are relative to the target directory. Files with the
+    /// same path get a counter appended, a template that renders to
+    /// an empty path falls back to the file name. The target then
+    /// contains only the files, without metadata and links, so it
+    /// can't be imported again.
+    #[arg(long, conflicts_with_all = [
+        "archive", "date_links", "tag_links", "folder_links",
+        "correspondent_links", "concerning_links", "field_links"
+    ])]
+    template: Option<PathTemplate>,
+
+    /// Makes the target mirror the query results. Items that are not
+    /// in the results anymore are deleted from the `items` directory,
+    /// as well as files of items that have been removed. Links that
+    /// don't match the current metadata of an item are removed from
+    /// the `by_*` directories. This implies `--all`.
+    #[arg(long, conflicts_with = "offset")]
+    prune: bool,
+
     /// Download everything into this directory.
-    #[arg(short, long)]
-    target: PathBuf,
+    #[arg(short, long, required_unless_present = "archive")]
+    target: Option<PathBuf>,
+
+    /// Write everything into this archive file instead of a
+    /// directory. The format is chosen by the file extension, it may
+    /// be `.zip`, `.tar.gz` or `.tgz`. The archive contains the same
+    /// `items` directory as an export into a directory. If links are
+    /// requested, they are written as `index.json` mapping each link
+    /// to the item's directory. A `manifest.json` is added as well,
+    /// so an extracted archive can be checked with `export verify`.
+    #[arg(long, conflicts_with_all = ["target", "since", "prune", "overwrite", "verify"])]
+    archive: Option<PathBuf>,
 
     /// The optional query string. If not given everything is
     /// exported. See <https://docspell.org/docs/query/>
     query: Option<String>,
 }
 
+#[derive(Parser, Debug)]
+pub enum ExportCommand {
+    #[command(version)]
+    Verify(verify::Input),
+}
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("An http error occurred: {}", source))]
     HttpClient { source: HttpError },
 
+    #[snafu(display("{}", source))]
+    Verify { source: verify::Error },
+
     #[snafu(display("Error writing data: {}", source))]
     WriteResult { source: SinkError },
 
@@ -127,107 +287,729 @@ pub enum Error {
 
     #[snafu(display("Not a directory: {}", path.display()))]
     NotADirectory { path: PathBuf },
+
+    #[snafu(display("Error accessing the export state in {}: {}", path.display(), source))]
+    State {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Error pruning {}: {}", path.display(), source))]
+    Prune {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Error creating hash for '{}': {}", path.display(), source))]
+    DigestFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Error writing archive {}: {}", path.display(), source))]
+    Archive {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+}
+
+impl Input {
+    /// The target directory. It is always present, unless exporting
+    /// into an archive.
+    fn target(&self) -> &Path {
+        self.target
+            .as_deref()
+            .expect("The target is required without --archive")
+    }
 }
 
 impl Cmd for Input {
     type CmdError = Error;
 
     fn exec(&self, ctx: &Context) -> Result<(), Error> {
-        let mut req = SearchReq {
-            offset: self.offset,
-            limit: self.limit,
-            with_details: true,
-            query: self.query.clone().unwrap_or_else(|| "".into()),
-            search_mode: SearchMode::Normal,
-        };
+        if let Some(ExportCommand::Verify(input)) = &self.subcmd {
+            return input.exec(ctx).context(VerifySnafu);
+        }
+        if let Some(file) = &self.archive {
+            return export_archive(file, self, ctx);
+        }
+        let mut state = ExportState::load(self.target()).context(StateSnafu {
+            path: self.target(),
+        })?;
+        let mut report = Report::default();
+        if self.template.is_some() {
+            report.reserve(&state);
+        }
 
-        let mut counter = 0;
-        loop {
-            let next = export(&req, self, ctx)?;
-            counter += next;
-            if self.all && next >= self.limit as usize {
-                req.offset += req.limit;
-            } else {
-                break;
+        let started = chrono::Local::now().date_naive();
+        let query = self.query.clone().unwrap_or_default();
+        // Whether all items of the query are exported in this run.
+        let mut complete = self.since.is_some();
+        if let Some(since) = self.since {
+            let date = match since {
+                Since::Date(date) => Some(date),
+                Since::Last => {
+                    let last = LastRun::load(self.target(), &query).and_then(|r| r.date());
+                    if last.is_none() {
+                        eprintln!("No previous export of this query found, exporting all items.");
+                    }
+                    last
+                }
+            };
+            export_since(date, self, ctx, &mut state, &mut report)?;
+        } else {
+            let mut req = SearchReq {
+                offset: self.offset,
+                limit: self.limit,
+                with_details: true,
+                query: self.query.clone().unwrap_or_else(|| "".into()),
+                search_mode: SearchMode::Normal,
+            };
+            // Pruning needs all items of the query, so it always
+            // starts at the beginning.
+            let resumable = self.all && !self.prune;
+            if let Some(progress) = Progress::load(self.target())
+                .filter(|p| resumable && p.query == req.query && p.offset > req.offset)
+            {
+                eprintln!(
+                    "Continuing interrupted export at offset {}.",
+                    progress.offset
+                );
+                req.offset = progress.offset;
             }
+            complete = (self.all || self.prune) && req.offset == 0;
+            loop {
+                let next = export(&req, self, ctx, &mut state, &mut report)?;
+                if (self.all || self.prune) && next >= self.limit as usize {
+                    req.offset += req.limit;
+                    if resumable {
+                        Progress {
+                            query: req.query.clone(),
+                            offset: req.offset,
+                        }
+                        .save(self.target())
+                        .context(StateSnafu {
+                            path: self.target(),
+                        })?;
+                    }
+                } else {
+                    break;
+                }
+            }
+            if resumable {
+                Progress::clear(self.target()).context(StateSnafu {
+                    path: self.target(),
+                })?;
+            }
+        }
+        if self.prune {
+            prune(self, &mut state, &mut report)?;
+        }
+        state.compact().context(StateSnafu {
+            path: self.target(),
+        })?;
+        Manifest::from_state(
+            self.target(),
+            &state,
+            self.template.is_some(),
+            &report.written,
+        )
+        .and_then(|m| m.write(self.target()))
+        .context(StateSnafu {
+            path: self.target(),
+        })?;
+        if complete {
+            LastRun::new(&query, started)
+                .save(self.target())
+                .context(StateSnafu {
+                    path: self.target(),
+                })?;
+        }
+        eprintln!(
+            "Exported {} items ({} new, {} changed, {} unchanged, {} removed).",
+            report.total(),
+            report.new,
+            report.changed,
+            report.unchanged,
+            report.removed
+        );
+        if self.prune {
+            eprintln!(
+                "Pruned {} items and {} links.",
+                report.pruned_items, report.pruned_links
+            );
         }
-        eprintln!("Exported {} items.", counter);
         Ok(())
     }
 }
 
-fn export(req: &SearchReq, opts: &Input, ctx: &Context) -> Result<usize, Error> {
+/// Exports all items of the query into an archive file. The state
+/// file is not used, as an archive is always written completely.
+fn export_archive(file: &Path, opts: &Input, ctx: &Context) -> Result<(), Error> {
+    let mut archive = ArchiveWriter::create(file).context(ArchiveSnafu { path: file })?;
+    let mut index = BTreeMap::new();
+    let mut count = 0;
+    let mut req = SearchReq {
+        offset: opts.offset,
+        limit: opts.limit,
+        with_details: true,
+        query: opts.query.clone().unwrap_or_default(),
+        search_mode: SearchMode::Normal,
+    };
+    loop {
+        let results = ctx
+            .client
+            .search(&ctx.opts.session, &req)
+            .context(HttpClientSnafu)?;
+        let mut next = 0;
+        for item in results.groups.into_iter().flat_map(|g| g.items) {
+            next += 1;
+            archive_item(item, file, &mut archive, &mut index, opts, ctx)?;
+        }
+        count += next;
+        if opts.all && next >= opts.limit as usize {
+            req.offset += req.limit;
+        } else {
+            break;
+        }
+    }
+    if !index.is_empty() {
+        archive
+            .start_file("index.json")
+            .context(ArchiveSnafu { path: file })?;
+        serde_json::to_writer_pretty(&mut archive, &index).context(JsonSnafu)?;
+    }
+    let manifest = archive.manifest().context(ArchiveSnafu { path: file })?;
+    archive
+        .start_file(MANIFEST_FILE)
+        .context(ArchiveSnafu { path: file })?;
+    serde_json::to_writer_pretty(&mut archive, &manifest).context(JsonSnafu)?;
+    archive.finish().context(ArchiveSnafu { path: file })?;
+    eprintln!("Exported {} items into {}.", count, file.display());
+    Ok(())
+}
+
+/// Writes the metadata and all files of an item into the archive and
+/// adds its links to the index.
+fn archive_item(
+    mut item: Item,
+    file: &Path,
+    archive: &mut ArchiveWriter,
+    index: &mut BTreeMap<String, String>,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    log::debug!("Archiving item {}/{}", item.id, item.name);
+    item.related = ctx
+        .client
+        .get_related_items(&ctx.opts.session, &item.id)
+        .context(HttpClientSnafu)?
+        .into_iter()
+        .map(|i| i.to_idname())
+        .collect();
+    let item_dir = format!("items/{}/{}", id_prefix(&item.id), item.id);
+    archive
+        .start_file(&format!("{}/metadata.json", item_dir))
+        .context(ArchiveSnafu { path: file })?;
+    serde_json::to_writer_pretty(&mut *archive, &item).context(JsonSnafu)?;
+
+    let mut names = HashSet::new();
+    for attach in Downloads::from_item(&item) {
+        let orig = attach
+            .get_original(&ctx.client, &ctx.opts.session)
+            .context(HttpClientSnafu)?;
+        if let Some(mut orig_file) = orig {
+            let orig_name = orig_file.get_filename().unwrap_or(attach.name);
+            let mut file_name = orig_name.clone();
+            let mut collision_counter = 1;
+            while !names.insert(file_name.clone()) {
+                log::debug!("Found name collision for: \"{}\"", file_name);
+                file_name = numbered_name(&orig_name, collision_counter);
+                collision_counter += 1;
+            }
+            archive
+                .start_file(&format!("{}/files/{}", item_dir, file_name))
+                .context(ArchiveSnafu { path: file })?;
+            orig_file.copy_to(archive).context(HttpClientSnafu)?;
+        }
+    }
+
+    let link_filename = link_filename(&item, opts);
+    for link_dir in link_dirs(&item, opts) {
+        let link_dir = link_dir.to_string_lossy();
+        let mut link = format!("{}/{}", link_dir, link_filename);
+        let mut collision_counter = 1;
+        while index.contains_key(&link) {
+            link = format!("{}/{} ({})", link_dir, link_filename, collision_counter);
+            collision_counter += 1;
+        }
+        index.insert(link, item_dir.clone());
+    }
+    export_message(item, Change::New, ctx)
+}
+
+/// Counts the exported items by how they compare to the previous
+/// export. It also collects all items of the query and the links to
+/// them, which is needed for pruning.
+#[derive(Debug, Default)]
+struct Report {
+    new: usize,
+    changed: usize,
+    unchanged: usize,
+    removed: usize,
+    pruned_items: usize,
+    pruned_links: usize,
+    items: HashSet<String>,
+    links: HashSet<PathBuf>,
+    templated: HashSet<PathBuf>,
+    /// The ids of items whose `metadata.json` or `removed.txt` has
+    /// been written in this run.
+    written: HashSet<String>,
+    /// The paths of files from the previous export by the id of their
+    /// item. Other items don't get these paths, even if they are
+    /// exported first, so that no file is overwritten.
+    reserved: HashMap<PathBuf, String>,
+}
+
+impl Report {
+    fn add(&mut self, change: Change) {
+        match change {
+            Change::New => self.new += 1,
+            Change::Changed => self.changed += 1,
+            Change::Unchanged => self.unchanged += 1,
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.new + self.changed + self.unchanged
+    }
+
+    /// Reserves the paths of all files in the export state for their
+    /// items.
+    fn reserve(&mut self, state: &ExportState) {
+        for id in state.ids() {
+            for f in state.get(&id).map(|e| e.files.as_slice()).unwrap_or(&[]) {
+                self.reserved.insert(PathBuf::from(&f.name), id.clone());
+            }
+        }
+    }
+
+    /// Returns whether the path is used by this export or reserved for
+    /// another item.
+    fn is_taken(&self, path: &Path, item_id: &str) -> bool {
+        self.templated.contains(path)
+            || self
+                .reserved
+                .get(path)
+                .map(|owner| owner != item_id)
+                .unwrap_or(false)
+    }
+}
+
+fn export(
+    req: &SearchReq,
+    opts: &Input,
+    ctx: &Context,
+    state: &mut ExportState,
+    report: &mut Report,
+) -> Result<usize, Error> {
     let results = ctx
         .client
         .search(&ctx.opts.session, req)
         .context(HttpClientSnafu)?;
     let mut item_counter = 0;
-    let items = opts.target.join("items");
-    let by_date = opts.target.join("by_date");
-    let by_tag = opts.target.join("by_tag");
-    let by_folder = opts.target.join("by_folder");
-    let by_corr = opts.target.join("by_correspondent");
     for g in results.groups {
         for item in g.items {
             item_counter += 1;
-            let item_dir = items.join(&item.id[0..2]).join(&item.id);
-            export_item(&item, opts.overwrite, &item_dir, ctx)?;
+            export_one(item, opts, ctx, state, report)?;
+        }
+    }
+    Ok(item_counter)
+}
 
-            if opts.date_links {
-                let link_dir = by_date.join(format_date_by(item.date, "%Y-%m"));
-                make_links(&item, opts, &item_dir, &link_dir)?;
-            }
-            if opts.correspondent_links {
-                let corr_opt = item.corr_org.as_ref().or(item.corr_person.as_ref());
-                if let Some(corr) = corr_opt {
-                    let link_dir = by_corr.join(file::safe_filename(&corr.name));
-                    make_links(&item, opts, &item_dir, &link_dir)?;
+/// Exports the items of the query created on or after the given
+/// date, or all items without a date, and updates or removes the
+/// ones from a previous export.
+///
+/// To find the items that have been removed on the server, the ids
+/// of all items of the query are fetched without details, unless all
+/// items have been exported anyway.
+fn export_since(
+    since: Option<NaiveDate>,
+    opts: &Input,
+    ctx: &Context,
+    state: &mut ExportState,
+    report: &mut Report,
+) -> Result<(), Error> {
+    let query = opts.query.clone().unwrap_or_default();
+    let req = SearchReq {
+        offset: 0,
+        limit: opts.limit,
+        with_details: true,
+        query: match since {
+            Some(date) => format!("{} created>={}", query, date.format("%Y-%m-%d"))
+                .trim()
+                .to_string(),
+            None => query.clone(),
+        },
+        search_mode: SearchMode::Normal,
+    };
+    search_all(req.clone(), ctx, |item| {
+        export_one(item, opts, ctx, state, report)
+    })?;
+
+    let mut present = report.items.clone();
+    let req = SearchReq {
+        with_details: false,
+        query,
+        ..req
+    };
+    if since.is_some() {
+        search_all(req.clone(), ctx, |item| {
+            present.insert(item.id);
+            Ok(())
+        })?;
+    }
+    let missing: Vec<String> = state
+        .ids()
+        .into_iter()
+        .filter(|id| !present.contains(id))
+        .collect();
+    if !missing.is_empty() {
+        let mut trashed = HashSet::new();
+        let trashed_req = SearchReq {
+            search_mode: SearchMode::Trashed,
+            ..req
+        };
+        search_all(trashed_req, ctx, |item| {
+            trashed.insert(item.id);
+            Ok(())
+        })?;
+        for id in missing {
+            let reason = if trashed.contains(&id) {
+                "trashed"
+            } else {
+                "deleted"
+            };
+            remove_item(&id, reason, opts, state, report)?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the search and passes each item of all result pages to `f`.
+fn search_all<F>(mut req: SearchReq, ctx: &Context, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(Item) -> Result<(), Error>,
+{
+    loop {
+        let results = ctx
+            .client
+            .search(&ctx.opts.session, &req)
+            .context(HttpClientSnafu)?;
+        let mut count = 0;
+        for item in results.groups.into_iter().flat_map(|g| g.items) {
+            count += 1;
+            f(item)?;
+        }
+        if count == 0 || count < req.limit as usize {
+            return Ok(());
+        }
+        req.offset += req.limit;
+    }
+}
+
+/// Marks or deletes an exported item that is not present on the
+/// server anymore.
+fn remove_item(
+    id: &str,
+    reason: &str,
+    opts: &Input,
+    state: &mut ExportState,
+    report: &mut Report,
+) -> Result<(), Error> {
+    let dir = item_dir(opts.target(), id);
+    match opts.removed.unwrap_or(RemovedAction::Mark) {
+        RemovedAction::Mark => {
+            if let Some(entry) = state.get(id).filter(|e| e.removed.is_none()).cloned() {
+                eprintln!("Marking {} item: {}", reason, id);
+                if dir.exists() {
+                    std::fs::write(dir.join(REMOVED_FILE), reason).context(CreateFileSnafu)?;
                 }
+                state
+                    .complete(ItemEntry {
+                        removed: Some(reason.to_string()),
+                        ..entry
+                    })
+                    .context(StateSnafu {
+                        path: opts.target(),
+                    })?;
+                report.written.insert(id.to_string());
+                report.removed += 1;
             }
-            if opts.tag_links {
-                for tag in &item.tags {
-                    let link_dir = by_tag.join(file::safe_filename(&tag.name));
-                    make_links(&item, opts, &item_dir, &link_dir)?;
-                }
+        }
+        RemovedAction::Delete => {
+            eprintln!("Deleting {} item: {}", reason, id);
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir).context(DeleteFileSnafu)?;
+            }
+            remove_templated_item(id, opts, state, report)?;
+            report.removed += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Removes the files of an item from the tree of the template and
+/// the item from the export state.
+fn remove_templated_item(
+    id: &str,
+    opts: &Input,
+    state: &mut ExportState,
+    report: &Report,
+) -> Result<(), Error> {
+    if let (Some(entry), true) = (state.get(id), opts.template.is_some()) {
+        for f in &entry.files {
+            remove_templated(opts.target(), &f.name, report)?;
+        }
+    }
+    state.remove(id);
+    Ok(())
+}
+
+/// The directories containing links to items.
+const LINK_DIRS: [&str; 6] = [
+    "by_date",
+    "by_tag",
+    "by_folder",
+    "by_correspondent",
+    "by_concerning",
+    "by_field",
+];
+
+/// The directory for tags without a category when grouping tag links
+/// by category.
+const NO_CATEGORY: &str = "none";
+
+/// Removes everything from the target that doesn't belong to the
+/// items and links of this export.
+fn prune(opts: &Input, state: &mut ExportState, report: &mut Report) -> Result<(), Error> {
+    if opts.template.is_some() {
+        for id in state.ids() {
+            if !report.items.contains(&id) {
+                eprintln!("Pruning item: {}", id);
+                remove_templated_item(&id, opts, state, report)?;
+                report.pruned_items += 1;
+            }
+        }
+    }
+    let items_dir = opts.target().join("items");
+    if items_dir.is_dir() {
+        for prefix in read_dir(&items_dir)? {
+            if !prefix.is_dir() {
+                continue;
             }
-            if opts.folder_links {
-                let folder_opt = item
-                    .folder
-                    .as_ref()
-                    .map(|f| file::safe_filepath(&f.name, &opts.folder_delimiter));
-                if let Some(folder_name) = folder_opt {
-                    let link_dir = by_folder.join(folder_name);
-                    make_links(&item, opts, &item_dir, &link_dir)?;
+            for dir in read_dir(&prefix)? {
+                let id = dir
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if report.items.contains(&id) {
+                    prune_files(&dir, state.get(&id).map(|e| e.files.as_slice()))?;
+                } else {
+                    eprintln!("Pruning item: {}", id);
+                    std::fs::remove_dir_all(&dir).context(PruneSnafu { path: &dir })?;
+                    state.remove(&id);
+                    report.pruned_items += 1;
                 }
             }
-            export_message(item, ctx)?;
+            remove_if_empty(&prefix)?;
         }
     }
-    Ok(item_counter)
+    for name in LINK_DIRS {
+        let dir = opts.target().join(name);
+        if dir.is_dir() {
+            report.pruned_links += prune_links(&dir, &report.links)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes files of an item that are not known to the export state.
+fn prune_files(item_dir: &Path, known: Option<&[FileEntry]>) -> Result<(), Error> {
+    let files_dir = item_dir.join("files");
+    if let (Some(known), true) = (known, files_dir.is_dir()) {
+        for file in read_dir(&files_dir)? {
+            let name = file.file_name().map(|n| n.to_string_lossy().to_string());
+            if !known.iter().any(|f| Some(&f.name) == name.as_ref()) {
+                log::info!("Pruning file: {}", file.display());
+                std::fs::remove_file(&file).context(PruneSnafu { path: &file })?;
+            }
+        }
+    }
+    Ok(())
 }
 
-fn export_message(item: Item, ctx: &Context) -> Result<(), Error> {
+/// Removes all links below `dir` that are not in `keep` and all
+/// directories that become empty. Returns the number of removed
+/// links.
+fn prune_links(dir: &Path, keep: &HashSet<PathBuf>) -> Result<usize, Error> {
+    let mut count = 0;
+    for path in read_dir(dir)? {
+        let meta = std::fs::symlink_metadata(&path).context(PruneSnafu { path: &path })?;
+        if meta.file_type().is_symlink() {
+            if !keep.contains(&path) {
+                log::debug!("Pruning link: {}", path.display());
+                std::fs::remove_file(&path).context(PruneSnafu { path: &path })?;
+                count += 1;
+            }
+        } else if meta.is_dir() {
+            count += prune_links(&path, keep)?;
+            remove_if_empty(&path)?;
+        }
+    }
+    Ok(count)
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    std::fs::read_dir(dir)
+        .and_then(|entries| entries.map(|e| e.map(|e| e.path())).collect())
+        .context(PruneSnafu { path: dir })
+}
+
+fn remove_if_empty(dir: &Path) -> Result<(), Error> {
+    if read_dir(dir)?.is_empty() {
+        std::fs::remove_dir(dir).context(PruneSnafu { path: dir })?;
+    }
+    Ok(())
+}
+
+fn item_dir(target: &Path, id: &str) -> PathBuf {
+    target.join("items").join(id_prefix(id)).join(id)
+}
+
+/// The first two characters of the item id, used as the directory
+/// below `items` that contains the item's directory.
+fn id_prefix(id: &str) -> &str {
+    id.get(0..2).unwrap_or(id)
+}
+
+/// Exports a single item, unless it didn't change since the last
+/// export, and creates the links to it.
+fn export_one(
+    mut item: Item,
+    opts: &Input,
+    ctx: &Context,
+    state: &mut ExportState,
+    report: &mut Report,
+) -> Result<(), Error> {
+    let item_dir = item_dir(opts.target(), &item.id);
+    report.items.insert(item.id.clone());
+    let item_digest = state::item_digest(&item).context(StateSnafu {
+        path: opts.target(),
+    })?;
+    let files_dir = match opts.template {
+        Some(_) => opts.target().to_path_buf(),
+        None => item_dir.join("files"),
+    };
+    let change = state.compare(&item_digest, &files_dir, &item.id, opts.verify);
+    report.add(change);
+    if let Some(template) = &opts.template {
+        let known = state.get(&item.id).map(|e| e.files.clone());
+        let files = export_templated(&item, template, opts, known.as_deref(), report, ctx)?;
+        if change != Change::Unchanged || known.as_ref() != Some(&files) {
+            state
+                .complete(ItemEntry {
+                    id: item.id.clone(),
+                    digest: item_digest,
+                    files,
+                    removed: None,
+                })
+                .context(StateSnafu {
+                    path: opts.target(),
+                })?;
+        }
+    } else if change == Change::Unchanged && !opts.overwrite {
+        log::debug!("Skip unchanged item {}/{}", item.id, item.name);
+    } else {
+        // Related items are only written into the metadata, so they
+        // are fetched only for items that are exported.
+        item.related = ctx
+            .client
+            .get_related_items(&ctx.opts.session, &item.id)
+            .context(HttpClientSnafu)?
+            .into_iter()
+            .map(|i| i.to_idname())
+            .collect();
+        let files = export_item(&item, change, opts, &item_dir, state, ctx)?;
+        report.written.insert(item.id.clone());
+        state
+            .complete(ItemEntry {
+                id: item.id.clone(),
+                digest: item_digest,
+                files,
+                removed: None,
+            })
+            .context(StateSnafu {
+                path: opts.target(),
+            })?;
+    }
+
+    for link_dir in link_dirs(&item, opts) {
+        let link_dir = opts.target().join(link_dir);
+        report
+            .links
+            .insert(make_links(&item, opts, &item_dir, &link_dir)?);
+    }
+    export_message(item, change, ctx)
+}
+
+/// Prints a message about the exported item, or the item itself for
+/// the json and lisp formats. Unchanged items are only printed in
+/// the latter case.
+fn export_message(item: Item, change: Change, ctx: &Context) -> Result<(), Error> {
+    let prefix = match change {
+        Change::New => "Exported",
+        Change::Changed => "Updated",
+        Change::Unchanged => "Unchanged",
+    };
     match ctx.format() {
-        Format::Tabular => eprintln!("Exported item: {}", item.name),
-        Format::Csv => eprintln!("Exported item: {}", item.name),
+        Format::Tabular | Format::Csv if change == Change::Unchanged => {
+            log::debug!("{} item: {}", prefix, item.name)
+        }
+        Format::Tabular | Format::Csv => eprintln!("{} item: {}", prefix, item.name),
         _ => ctx.write_result(item).context(WriteResultSnafu)?,
     }
 
     Ok(())
 }
 
-fn export_item(item: &Item, overwrite: bool, item_dir: &Path, ctx: &Context) -> Result<(), Error> {
+/// Writes the metadata and downloads all files of the item. Returns
+/// the files of the item that are now present.
+fn export_item(
+    item: &Item,
+    change: Change,
+    opts: &Input,
+    item_dir: &Path,
+    state: &ExportState,
+    ctx: &Context,
+) -> Result<Vec<FileEntry>, Error> {
     log::debug!("Exporting item {}/{}", item.id, item.name);
     let meta_file = item_dir.join("metadata.json");
-    if meta_file.exists() && overwrite {
+    let overwrite = opts.overwrite;
+    if meta_file.exists() && (overwrite || change == Change::Changed) {
         log::debug!(
-            "Remove existing meta file {}, due to overwrite=true",
+            "Remove existing meta file {}, due to overwrite or change",
             meta_file.display()
         );
         std::fs::remove_file(&meta_file).context(DeleteFileSnafu)?;
     }
     if !item_dir.exists() {
-        std::fs::create_dir_all(&item_dir).context(CreateFileSnafu)?;
+        std::fs::create_dir_all(item_dir).context(CreateFileSnafu)?;
+    }
+    let removed_file = item_dir.join(REMOVED_FILE);
+    if removed_file.exists() {
+        std::fs::remove_file(&removed_file).context(DeleteFileSnafu)?;
     }
     if !&meta_file.exists() {
         let file = std::fs::File::create(&meta_file).context(CreateFileSnafu)?;
@@ -241,31 +1023,276 @@ fn export_item(item: &Item, overwrite: bool, item_dir: &Path, ctx: &Context) ->
     if !file_dir.exists() {
         std::fs::create_dir_all(&file_dir).context(CreateFileSnafu)?;
     }
+    let known_files = state
+        .get(&item.id)
+        .map(|e| e.files.as_slice())
+        .unwrap_or(&[]);
+    let mut files = Vec::new();
     let dl = Downloads::from_item(item);
     for attach in dl {
+        let known = known_files.iter().find(|f| f.id == attach.id);
+        if let (Some(known), false) = (known, overwrite) {
+            if known.is_intact(&file_dir, opts.verify) {
+                log::debug!("Skipping already exported file {}", known.name);
+                files.push(known.clone());
+                continue;
+            }
+        }
+
         log::debug!("Saving attachment: {}/{}", attach.id, attach.name);
         let orig = attach
             .get_original(&ctx.client, &ctx.opts.session)
             .context(HttpClientSnafu)?;
         if let Some(mut orig_file) = orig {
             let file_name = orig_file.get_filename().unwrap_or(attach.name);
-            let file_path = file_dir.join(file_name);
-            if file_path.exists() && overwrite {
+            let file_path = file_dir.join(&file_name);
+            // A file of a previous export that has been modified since
+            // is downloaded again.
+            let modified = known.map(|f| f.name == file_name).unwrap_or(false);
+            if file_path.exists() && (overwrite || modified) {
                 log::debug!(
-                    "Removing existing {}, due to overwrite=true",
+                    "Removing existing {}, due to overwrite or modification",
                     file_path.display()
                 );
-                std::fs::remove_file(&meta_file).context(DeleteFileSnafu)?;
+                std::fs::remove_file(&file_path).context(DeleteFileSnafu)?;
             }
             if !file_path.exists() {
-                let file = std::fs::File::create(&file_path).context(CreateFileSnafu)?;
+                // Download into a temporary file first, so an
+                // interrupted export doesn't leave incomplete files.
+                let part_path = file_dir.join(format!("{}.part", file_name));
+                let file = std::fs::File::create(&part_path).context(CreateFileSnafu)?;
                 let mut fw = std::io::BufWriter::new(file);
                 orig_file.copy_to(&mut fw).context(HttpClientSnafu)?;
+                fw.flush().context(CreateFileSnafu)?;
+                drop(fw);
+                std::fs::rename(&part_path, &file_path).context(CreateFileSnafu)?;
             } else {
                 log::debug!("Skipping existing file {}", file_path.display());
             }
+            let entry = FileEntry::from_file(attach.id, file_name, &file_path)
+                .context(DigestFileSnafu { path: &file_path })?;
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+/// Returns the directories, relative to the export target, that
+/// contain links to the item.
+fn link_dirs(item: &Item, opts: &Input) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if opts.date_links {
+        dirs.push(Path::new("by_date").join(format_date_by(item.date, "%Y-%m")));
+    }
+    if opts.correspondent_links {
+        let corr_opt = item.corr_org.as_ref().or(item.corr_person.as_ref());
+        if let Some(corr) = corr_opt {
+            dirs.push(Path::new("by_correspondent").join(file::safe_filename(&corr.name)));
+        }
+    }
+    if opts.concerning_links {
+        let conc_opt = item.conc_person.as_ref().or(item.conc_equip.as_ref());
+        if let Some(conc) = conc_opt {
+            dirs.push(Path::new("by_concerning").join(file::safe_filename(&conc.name)));
+        }
+    }
+    if opts.tag_links {
+        for tag in &item.tags {
+            let tag_dir = if opts.tag_categories {
+                let category = tag.category.as_deref().unwrap_or(NO_CATEGORY);
+                Path::new("by_tag").join(file::safe_filename(category))
+            } else {
+                PathBuf::from("by_tag")
+            };
+            dirs.push(tag_dir.join(file::safe_filename(&tag.name)));
+        }
+    }
+    for field_name in &opts.field_links {
+        let field = item
+            .customfields
+            .iter()
+            .find(|f| &f.name == field_name || f.label.as_ref() == Some(field_name));
+        if let Some(field) = field.filter(|f| !f.value.is_empty()) {
+            dirs.push(
+                Path::new("by_field")
+                    .join(file::safe_filename(field_name))
+                    .join(file::safe_filename(&field.value)),
+            );
+        }
+    }
+    if opts.folder_links {
+        let folder_opt = item
+            .folder
+            .as_ref()
+            .map(|f| file::safe_filepath(&f.name, &opts.folder_delimiter));
+        if let Some(folder_name) = folder_opt {
+            dirs.push(Path::new("by_folder").join(folder_name));
+        }
+    }
+    dirs
+}
+
+fn link_filename(item: &Item, opts: &Input) -> String {
+    match opts.link_naming.unwrap_or_default() {
+        LinkNaming::Id => item.id.clone(),
+        LinkNaming::Name => file::safe_filename(&item.name),
+    }
+}
+
+/// Downloads the files of an item to the paths from the template.
+/// Files of a previous export are kept if their checksum matches, or
+/// moved if only their path changed. Returns the files of the item
+/// that are now present.
+fn export_templated(
+    item: &Item,
+    template: &PathTemplate,
+    opts: &Input,
+    known: Option<&[FileEntry]>,
+    report: &mut Report,
+    ctx: &Context,
+) -> Result<Vec<FileEntry>, Error> {
+    let target = opts.target();
+    let known = known.unwrap_or(&[]);
+    let mut files = Vec::new();
+    for attach in Downloads::from_item(item) {
+        let rel_path = template_path(template.render(item, &attach.name), &item.id, report);
+        let name = slash_path(&rel_path);
+        let path = target.join(&rel_path);
+        let prev = known.iter().find(|f| f.id == attach.id);
+        if let (Some(prev), false) = (prev, opts.overwrite) {
+            if prev.is_intact(target, opts.verify) {
+                let mut entry = FileEntry {
+                    name,
+                    ..prev.clone()
+                };
+                if prev.name != entry.name {
+                    place_file(target, &prev.name, &rel_path, report)?;
+                    entry
+                        .update_mtime(&path)
+                        .context(DigestFileSnafu { path: &path })?;
+                }
+                log::debug!("Skipping already exported file {}", entry.name);
+                files.push(entry);
+                continue;
+            }
+        }
+
+        log::debug!("Saving attachment: {}/{}", attach.id, attach.name);
+        let orig = attach
+            .get_original(&ctx.client, &ctx.opts.session)
+            .context(HttpClientSnafu)?;
+        if let Some(mut orig_file) = orig {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).context(CreateFileSnafu)?;
+            }
+            let part_path = path.with_file_name(format!(
+                "{}.part",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            let file = std::fs::File::create(&part_path).context(CreateFileSnafu)?;
+            let mut fw = std::io::BufWriter::new(file);
+            orig_file.copy_to(&mut fw).context(HttpClientSnafu)?;
+            fw.flush().context(CreateFileSnafu)?;
+            drop(fw);
+            std::fs::rename(&part_path, &path).context(CreateFileSnafu)?;
+            let entry = FileEntry::from_file(attach.id, name, &path)
+                .context(DigestFileSnafu { path: &path })?;
+            files.push(entry);
         }
     }
+    // files at paths that are not used anymore
+    for prev in known {
+        if !files.iter().any(|f| f.name == prev.name) {
+            remove_templated(target, &prev.name, report)?;
+        }
+    }
+    Ok(files)
+}
+
+/// Returns the path for a rendered template. If another file of this
+/// export already uses it, or it belongs to another item of the
+/// previous export, a counter is appended to the file name.
+fn template_path(rendered: PathBuf, item_id: &str, report: &mut Report) -> PathBuf {
+    let mut path = rendered.clone();
+    let mut collision_counter = 1;
+    while report.is_taken(&path, item_id) {
+        log::debug!("Found name collision for: \"{}\"", path.display());
+        let name = rendered
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        path = rendered.with_file_name(numbered_name(&name, collision_counter));
+        collision_counter += 1;
+    }
+    report.templated.insert(path.clone());
+    path
+}
+
+/// Puts the counter before the extension of the file name, like
+/// `bill (1).pdf`.
+fn numbered_name(name: &str, counter: u32) -> String {
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match path.extension() {
+        Some(ext) => format!("{} ({}).{}", stem, counter, ext.to_string_lossy()),
+        None => format!("{} ({})", stem, counter),
+    }
+}
+
+/// Returns the path with `/` as separator, as used in the export
+/// state and manifest.
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Moves a file of a previous export to its new path. It is copied
+/// instead, if another file of this export uses the old path now.
+fn place_file(target: &Path, from: &str, to: &Path, report: &Report) -> Result<(), Error> {
+    let from_path = target.join(from);
+    let to_path = target.join(to);
+    log::debug!("Moving {} to {}", from_path.display(), to_path.display());
+    if let Some(parent) = to_path.parent() {
+        std::fs::create_dir_all(parent).context(CreateFileSnafu)?;
+    }
+    if report.templated.contains(Path::new(from)) {
+        std::fs::copy(&from_path, &to_path).context(CreateFileSnafu)?;
+    } else {
+        std::fs::rename(&from_path, &to_path).context(CreateFileSnafu)?;
+        remove_empty_parents(target, &from_path)?;
+    }
+    Ok(())
+}
+
+/// Removes a file of a previous export, unless another file of this
+/// export uses its path now. Directories that become empty are
+/// removed as well.
+fn remove_templated(target: &Path, name: &str, report: &Report) -> Result<(), Error> {
+    let path = target.join(name);
+    if !report.templated.contains(Path::new(name)) && path.is_file() {
+        log::info!("Removing file: {}", path.display());
+        std::fs::remove_file(&path).context(DeleteFileSnafu)?;
+        remove_empty_parents(target, &path)?;
+    }
+    Ok(())
+}
+
+/// Removes all empty directories from the parent of `path` up to
+/// `target`.
+fn remove_empty_parents(target: &Path, path: &Path) -> Result<(), Error> {
+    let mut dir = path.parent();
+    while let Some(d) = dir.filter(|d| *d != target && d.starts_with(target) && d.is_dir()) {
+        if !read_dir(d)?.is_empty() {
+            break;
+        }
+        std::fs::remove_dir(d).context(PruneSnafu { path: d })?;
+        dir = d.parent();
+    }
     Ok(())
 }
 
@@ -274,16 +1301,13 @@ fn make_links(
     opts: &Input,
     link_target: &Path,
     link_name_path: &Path,
-) -> Result<(), Error> {
+) -> Result<PathBuf, Error> {
     if !link_name_path.exists() {
-        std::fs::create_dir_all(&link_name_path).context(CreateFileSnafu)?;
+        std::fs::create_dir_all(link_name_path).context(CreateFileSnafu)?;
     }
-    let link_filename = match opts.link_naming.unwrap_or_default() {
-        LinkNaming::Id => item.id.clone(),
-        LinkNaming::Name => file::safe_filename(&item.name),
-    };
+    let link_filename = link_filename(item, opts);
 
-    let rel_link_target = pathdiff::diff_paths(&link_target, &link_name_path).unwrap();
+    let rel_link_target = pathdiff::diff_paths(link_target, link_name_path).unwrap();
     // Append the item's id as link name on the link's path.
     let mut link_name = link_name_path.join(&link_filename);
     let mut collision_counter = 1;
@@ -315,9 +1339,167 @@ fn make_links(
     };
 
     if create_link {
-        file::symlink(rel_link_target, link_name).context(SymlinkSnafu)?;
+        file::symlink(rel_link_target, &link_name).context(SymlinkSnafu)?;
     } else {
         log::debug!("Skip existing link: {}", link_target.display());
     }
-    Ok(())
+    Ok(link_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_entry(name: &str) -> FileEntry {
+        FileEntry {
+            id: name.into(),
+            name: name.into(),
+            size: 0,
+            sha256: String::new(),
+            mtime: None,
+        }
+    }
+
+    #[test]
+    fn unit_prune_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = dir.path().join("files");
+        std::fs::create_dir_all(&files).unwrap();
+        std::fs::write(files.join("a.pdf"), "a").unwrap();
+        std::fs::write(files.join("old.pdf"), "old").unwrap();
+
+        prune_files(dir.path(), None).unwrap();
+        assert!(files.join("old.pdf").exists());
+
+        prune_files(dir.path(), Some(&[file_entry("a.pdf")])).unwrap();
+        assert!(files.join("a.pdf").exists());
+        assert!(!files.join("old.pdf").exists());
+    }
+
+    #[test]
+    fn unit_prune_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let item = dir.path().join("items").join("item1");
+        std::fs::create_dir_all(&item).unwrap();
+        let by_tag = dir.path().join("by_tag");
+        for tag in ["bill", "paid"] {
+            std::fs::create_dir_all(by_tag.join(tag)).unwrap();
+            file::symlink(&item, by_tag.join(tag).join("item1")).unwrap();
+        }
+
+        let keep: HashSet<PathBuf> = [by_tag.join("bill").join("item1")].into();
+        assert_eq!(prune_links(&by_tag, &keep).unwrap(), 1);
+        assert!(by_tag.join("bill").join("item1").is_symlink());
+        assert!(!by_tag.join("paid").exists());
+        assert!(item.is_dir());
+    }
+
+    fn test_item() -> Item {
+        serde_json::from_str(
+            r#"{"id": "item1", "name": "Invoice", "state": "created", "date": 1706659200000,
+                "source": "web", "attachments": [], "highlighting": [],
+                "corrOrg": {"id": "o1", "name": "ACME"},
+                "concEquipment": {"id": "e1", "name": "Car"},
+                "tags": [{"id": "t1", "name": "bill", "category": "doctype", "created": 0},
+                         {"id": "t2", "name": "paid", "created": 0}],
+                "customfields": [{"id": "c1", "name": "contract", "label": "Contract No",
+                                  "ftype": "text", "value": "C-12/3"}]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn unit_link_dirs() {
+        let opts = Input::try_parse_from([
+            "export",
+            "--target",
+            "out",
+            "--tag-links",
+            "--tag-categories",
+            "--concerning-links",
+            "--correspondent-links",
+            "--field-links",
+            "Contract No",
+            "--field-links",
+            "missing",
+        ])
+        .unwrap();
+        let dirs = link_dirs(&test_item(), &opts);
+        let expected: Vec<PathBuf> = [
+            "by_correspondent/ACME",
+            "by_concerning/Car",
+            "by_tag/doctype/bill",
+            "by_tag/none/paid",
+            "by_field/Contract No/C-12-3",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        assert_eq!(dirs, expected);
+    }
+
+    #[test]
+    fn unit_make_links_collision() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts =
+            Input::try_parse_from(["export", "--target", "out", "--link-naming", "name"]).unwrap();
+        let item = test_item();
+        let link_dir = dir.path().join("by_tag").join("bill");
+        let first = dir.path().join("items").join("item1");
+        let second = dir.path().join("items").join("item2");
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+
+        let link1 = make_links(&item, &opts, &first, &link_dir).unwrap();
+        let link2 = make_links(&item, &opts, &second, &link_dir).unwrap();
+        assert_eq!(link1, link_dir.join("Invoice"));
+        assert_eq!(link2, link_dir.join("Invoice (1)"));
+        // linking the same item again doesn't create another link
+        assert_eq!(make_links(&item, &opts, &second, &link_dir).unwrap(), link2);
+        assert_eq!(read_dir(&link_dir).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn unit_templated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path();
+        std::fs::create_dir_all(target.join("2023/acme")).unwrap();
+        std::fs::write(target.join("2023/acme/bill.pdf"), "bill").unwrap();
+        std::fs::write(target.join("2023/acme/letter.pdf"), "letter").unwrap();
+
+        let mut report = Report::default();
+        let first = template_path(PathBuf::from("2024/acme/bill.pdf"), "a", &mut report);
+        let second = template_path(PathBuf::from("2024/acme/bill.pdf"), "a", &mut report);
+        assert_eq!(slash_path(&second), "2024/acme/bill (1).pdf");
+        assert_eq!(numbered_name("README", 2), "README (2)");
+
+        place_file(target, "2023/acme/bill.pdf", &first, &report).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(target.join("2024/acme/bill.pdf")).unwrap(),
+            "bill"
+        );
+        assert!(!target.join("2023/acme/bill.pdf").exists());
+
+        // paths used by this export are kept
+        template_path(PathBuf::from("2023/acme/letter.pdf"), "a", &mut report);
+        place_file(target, "2023/acme/letter.pdf", &second, &report).unwrap();
+        remove_templated(target, "2023/acme/letter.pdf", &report).unwrap();
+        assert!(target.join("2023/acme/letter.pdf").exists());
+        assert!(target.join("2024/acme/bill (1).pdf").exists());
+
+        remove_templated(target, "2024/acme/bill (1).pdf", &Report::default()).unwrap();
+        remove_templated(target, "2023/acme/letter.pdf", &Report::default()).unwrap();
+        assert!(!target.join("2023").exists());
+        assert!(target.join("2024/acme/bill.pdf").exists());
+
+        // paths of the previous export stay with their item
+        let mut report = Report::default();
+        report
+            .reserved
+            .insert(PathBuf::from("2024/acme/bill.pdf"), "a".into());
+        let other = template_path(PathBuf::from("2024/acme/bill.pdf"), "b", &mut report);
+        assert_eq!(slash_path(&other), "2024/acme/bill (1).pdf");
+        let own = template_path(PathBuf::from("2024/acme/bill.pdf"), "a", &mut report);
+        assert_eq!(slash_path(&own), "2024/acme/bill.pdf");
+    }
 }
@@ -0,0 +1,198 @@
+//! Writes an export into a single archive file.
+//!
+//! The archive contains the same `items/` layout as an export into a
+//! directory. The format is chosen by the file extension: `.zip`
+//! creates a zip file, `.tar.gz` or `.tgz` a gzip compressed tar file.
+//! Size and checksum of all files below `items/` are recorded for the
+//! manifest.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use super::manifest::{Manifest, ManifestEntry};
+
+/// Writes files into an archive. Like with [`ZipWriter`], a file is
+/// started with [`ArchiveWriter::start_file`] and its contents are
+/// written via the [`Write`] impl.
+///
+/// Zip files are streamed into the archive. Tar files require the
+/// size of an entry upfront, so the contents are spooled to a
+/// temporary file until the next file is started.
+pub struct ArchiveWriter {
+    inner: Inner,
+    pending: Option<(String, File)>,
+    current: Option<(String, Sha256, u64)>,
+    files: Vec<ManifestEntry>,
+}
+
+enum Inner {
+    Zip(ZipWriter<File>),
+    TarGz(tar::Builder<GzEncoder<BufWriter<File>>>),
+}
+
+impl ArchiveWriter {
+    /// Creates the archive file. Fails if the file extension doesn't
+    /// denote a supported format.
+    pub fn create(path: &Path) -> Result<ArchiveWriter, io::Error> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let inner = if name.ends_with(".zip") {
+            Inner::Zip(ZipWriter::new(File::create(path)?))
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            let out = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+            Inner::TarGz(tar::Builder::new(out))
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unsupported archive format, use one of .zip, .tar.gz or .tgz",
+            ));
+        };
+        Ok(ArchiveWriter {
+            inner,
+            pending: None,
+            current: None,
+            files: Vec::new(),
+        })
+    }
+
+    /// Starts a new file with the given name. The previous file is
+    /// completed.
+    pub fn start_file(&mut self, name: &str) -> Result<(), io::Error> {
+        self.end_file()?;
+        match &mut self.inner {
+            Inner::Zip(zw) => zw.start_file(name, FileOptions::default())?,
+            Inner::TarGz(_) => self.pending = Some((name.to_string(), tempfile::tempfile()?)),
+        }
+        self.current = Some((name.to_string(), Sha256::new(), 0));
+        Ok(())
+    }
+
+    /// Completes the current file and returns the manifest of all
+    /// files written below `items/` so far.
+    pub fn manifest(&mut self) -> Result<Manifest, io::Error> {
+        self.end_file()?;
+        Ok(Manifest {
+            files: self.files.clone(),
+        })
+    }
+
+    /// Writes the end of the archive and flushes it to disk.
+    pub fn finish(mut self) -> Result<(), io::Error> {
+        self.end_file()?;
+        match self.inner {
+            Inner::Zip(mut zw) => zw.finish()?.flush(),
+            Inner::TarGz(builder) => builder.into_inner()?.finish()?.flush(),
+        }
+    }
+
+    fn end_file(&mut self) -> Result<(), io::Error> {
+        let current = self.current.take();
+        if let Some((path, hasher, size)) = current.filter(|(p, _, _)| p.starts_with("items/")) {
+            self.files.push(ManifestEntry {
+                path,
+                size,
+                sha256: hex::encode(hasher.finalize()),
+            });
+        }
+        if let (Inner::TarGz(builder), Some((name, mut data))) =
+            (&mut self.inner, self.pending.take())
+        {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.stream_position()?);
+            header.set_mode(0o644);
+            header.set_mtime(now_secs());
+            data.rewind()?;
+            builder.append_data(&mut header, name, data)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for ArchiveWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = match (&mut self.inner, &mut self.pending) {
+            (Inner::Zip(zw), _) => zw.write(buf)?,
+            (Inner::TarGz(_), Some((_, data))) => data.write(buf)?,
+            (Inner::TarGz(_), None) => return Err(io::Error::other("No file has been started")),
+        };
+        if let Some((_, hasher, size)) = &mut self.current {
+            hasher.update(&buf[..n]);
+            *size += n as u64;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            Inner::Zip(zw) => zw.flush(),
+            Inner::TarGz(_) => Ok(()),
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn unit_archive_tar_gz() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("export.tar.gz");
+        let mut aw = ArchiveWriter::create(&file).unwrap();
+        aw.start_file("items/ab/abc/metadata.json").unwrap();
+        aw.write_all(b"{}").unwrap();
+        aw.start_file("items/ab/abc/files/large.bin").unwrap();
+        for _ in 0..64 {
+            aw.write_all(&[7; 4096]).unwrap();
+        }
+        let manifest = aw.manifest().unwrap();
+        aw.finish().unwrap();
+
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["items/ab/abc/metadata.json", "items/ab/abc/files/large.bin"]
+        );
+        assert_eq!(manifest.files[1].size, 64 * 4096);
+        assert_eq!(
+            manifest.files[0].sha256,
+            crate::util::digest::digest::<Sha256, _>(&mut "{}".as_bytes()).unwrap()
+        );
+
+        let gz = flate2::read::GzDecoder::new(File::open(&file).unwrap());
+        let mut archive = tar::Archive::new(gz);
+        let mut entries = archive.entries().unwrap();
+        let mut entry = entries.next().unwrap().unwrap();
+        assert_eq!(
+            entry.path().unwrap().to_str(),
+            Some("items/ab/abc/metadata.json")
+        );
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "{}");
+
+        let mut entry = entries.next().unwrap().unwrap();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data.len(), 64 * 4096);
+        assert!(data.iter().all(|b| *b == 7));
+        assert!(entries.next().is_none());
+    }
+}
@@ -0,0 +1,278 @@
+//! A manifest of all exported files.
+//!
+//! The manifest lists every file below the `items` directory of an
+//! export with its size and SHA-256 checksum. It is written at the end
+//! of each export and allows to check the integrity of the export
+//! later, see [`verify`].
+
+use prettytable::{row, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::state::ExportState;
+use super::{id_prefix, REMOVED_FILE};
+use crate::cli::sink::Sink;
+use crate::cli::table::{mk_table, AsTable};
+use crate::util::digest;
+
+/// The name of the manifest file in the export target.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The path relative to the export target, using `/` as separator.
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// The result of verifying an export against its manifest.
+#[derive(Debug, Default, Serialize)]
+pub struct Verification {
+    /// The number of files that are present and unchanged.
+    pub ok: usize,
+    pub missing: Vec<String>,
+    pub corrupt: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty() && self.extra.is_empty()
+    }
+}
+
+impl AsTable for Verification {
+    fn to_table(&self) -> Table {
+        let mut table = mk_table();
+        table.set_titles(row![bFg => "status", "file"]);
+        for (status, files) in [
+            ("missing", &self.missing),
+            ("corrupt", &self.corrupt),
+            ("extra", &self.extra),
+        ] {
+            for file in files {
+                table.add_row(row![status, file]);
+            }
+        }
+        table
+    }
+}
+impl Sink for Verification {}
+
+impl Manifest {
+    /// Creates the manifest for all items in the export state. The
+    /// size and checksum of the attachments are taken from the state,
+    /// as recorded when they were downloaded, so that files changed
+    /// since then are found when verifying. The metadata files are
+    /// hashed only for the `written` items, for the others they are
+    /// taken from the previous manifest. If `templated` is set, the
+    /// file names in the state are paths relative to the target.
+    pub fn from_state(
+        target: &Path,
+        state: &ExportState,
+        templated: bool,
+        written: &HashSet<String>,
+    ) -> Result<Manifest, io::Error> {
+        let previous: HashMap<String, ManifestEntry> = match Manifest::read(target) {
+            Ok(m) => m.files.into_iter().map(|e| (e.path.clone(), e)).collect(),
+            Err(_) => HashMap::new(),
+        };
+        let mut files = Vec::new();
+        for id in state.ids() {
+            let dir = format!("items/{}/{}", id_prefix(&id), id);
+            for name in ["metadata.json", REMOVED_FILE] {
+                let path = format!("{}/{}", dir, name);
+                match previous.get(&path) {
+                    Some(entry) if !written.contains(&id) => files.push(entry.clone()),
+                    _ => {
+                        let file = target.join(&path);
+                        if file.is_file() {
+                            files.push(ManifestEntry {
+                                size: std::fs::metadata(&file)?.len(),
+                                sha256: digest::digest_file_sha256(&file)?,
+                                path,
+                            });
+                        }
+                    }
+                }
+            }
+            for f in state.get(&id).map(|e| e.files.as_slice()).unwrap_or(&[]) {
+                files.push(ManifestEntry {
+                    path: if templated {
+                        f.name.clone()
+                    } else {
+                        format!("{}/files/{}", dir, f.name)
+                    },
+                    size: f.size,
+                    sha256: f.sha256.clone(),
+                });
+            }
+        }
+        Ok(Manifest { files })
+    }
+
+    pub fn read(target: &Path) -> Result<Manifest, io::Error> {
+        let reader = BufReader::new(File::open(target.join(MANIFEST_FILE))?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Writes the manifest into the target directory, replacing an
+    /// existing one.
+    pub fn write(&self, target: &Path) -> Result<(), io::Error> {
+        let file = target.join(MANIFEST_FILE);
+        let tmp = file.with_extension("json.tmp");
+        {
+            let mut out = BufWriter::new(File::create(&tmp)?);
+            serde_json::to_writer_pretty(&mut out, self)?;
+            out.flush()?;
+        }
+        std::fs::rename(&tmp, &file)
+    }
+}
+
+/// Checks all files in the manifest by size and checksum and looks for
+/// files that are not in the manifest. These are searched in all top
+/// level directories of the manifest's files, which is only `items`
+/// unless the export used a template.
+pub fn verify(target: &Path, manifest: &Manifest) -> Result<Verification, io::Error> {
+    let mut result = Verification::default();
+    for entry in &manifest.files {
+        let file = target.join(&entry.path);
+        if !file.is_file() {
+            result.missing.push(entry.path.clone());
+        } else if std::fs::metadata(&file)?.len() != entry.size
+            || digest::digest_file_sha256(&file)? != entry.sha256
+        {
+            result.corrupt.push(entry.path.clone());
+        } else {
+            result.ok += 1;
+        }
+    }
+
+    let known: HashSet<&str> = manifest.files.iter().map(|e| e.path.as_str()).collect();
+    let roots: BTreeSet<&str> = manifest
+        .files
+        .iter()
+        .filter_map(|e| e.path.split_once('/').map(|(root, _)| root))
+        .chain(["items"])
+        .collect();
+    let mut present = Vec::new();
+    for root in roots {
+        list_files(target, root, &mut present)?;
+    }
+    present.sort();
+    result.extra = present
+        .into_iter()
+        .filter(|p| !known.contains(p.as_str()))
+        .collect();
+    Ok(result)
+}
+
+/// Collects all files below `target/path` as paths relative to
+/// `target`.
+fn list_files(target: &Path, path: &str, files: &mut Vec<String>) -> Result<(), io::Error> {
+    let dir = target.join(path);
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let child = format!("{}/{}", path, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            list_files(target, &child, files)?;
+        } else {
+            files.push(child);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_manifest_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path();
+        let files_dir = target.join("items/ab/abc/files");
+        std::fs::create_dir_all(&files_dir).unwrap();
+        std::fs::write(files_dir.join("a.pdf"), "a").unwrap();
+        std::fs::write(files_dir.join("b.pdf"), "b").unwrap();
+
+        let entry = |name: &str, content: &str| ManifestEntry {
+            path: format!("items/ab/abc/files/{}", name),
+            size: content.len() as u64,
+            sha256: digest::digest::<sha2::Sha256, _>(&mut content.as_bytes()).unwrap(),
+        };
+        let manifest = Manifest {
+            files: vec![
+                entry("a.pdf", "a"),
+                entry("b.pdf", "x"),
+                entry("c.pdf", "c"),
+            ],
+        };
+        std::fs::write(files_dir.join("d.pdf"), "d").unwrap();
+
+        let result = verify(target, &manifest).unwrap();
+        assert_eq!(result.ok, 1);
+        assert_eq!(result.corrupt, vec!["items/ab/abc/files/b.pdf"]);
+        assert_eq!(result.missing, vec!["items/ab/abc/files/c.pdf"]);
+        assert_eq!(result.extra, vec!["items/ab/abc/files/d.pdf"]);
+    }
+
+    #[test]
+    fn unit_manifest_from_state() {
+        use super::super::state::{FileEntry, ItemEntry};
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path();
+        let item_dir = target.join("items/a/a");
+        std::fs::create_dir_all(item_dir.join("files")).unwrap();
+        std::fs::write(item_dir.join("files/a.pdf"), "replaced").unwrap();
+        std::fs::write(item_dir.join("metadata.json"), "{}").unwrap();
+
+        let mut state = ExportState::load(target).unwrap();
+        state
+            .complete(ItemEntry {
+                id: "a".into(),
+                digest: "x".into(),
+                files: vec![FileEntry {
+                    id: "attach1".into(),
+                    name: "a.pdf".into(),
+                    size: 1,
+                    sha256: "recorded".into(),
+                    mtime: None,
+                }],
+                removed: None,
+            })
+            .unwrap();
+
+        // the attachment is taken from the state, the metadata is
+        // hashed only for written items
+        let written = HashSet::from(["a".to_string()]);
+        let manifest = Manifest::from_state(target, &state, false, &written).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.files[0].path, "items/a/a/metadata.json");
+        assert_eq!(manifest.files[1].path, "items/a/a/files/a.pdf");
+        assert_eq!(manifest.files[1].size, 1);
+        assert_eq!(manifest.files[1].sha256, "recorded");
+        manifest.write(target).unwrap();
+
+        std::fs::write(item_dir.join("metadata.json"), "{\"changed\": true}").unwrap();
+        let next = Manifest::from_state(target, &state, false, &HashSet::new()).unwrap();
+        assert_eq!(next.files[0].sha256, manifest.files[0].sha256);
+
+        let result = verify(target, &next).unwrap();
+        assert_eq!(result.corrupt.len(), 2);
+    }
+}
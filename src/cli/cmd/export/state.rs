@@ -0,0 +1,382 @@
+//! Keeps track of exported items.
+//!
+//! The state is stored in a journal file in the export target. Each
+//! line is a json object describing an item that has been exported
+//! completely: a digest of its metadata and the checksums of its
+//! files. When an export is interrupted, the next run can skip all
+//! items found in the journal and compare the others to find out
+//! what changed.
+//!
+//! Entries are appended once an item is done. A later entry for the
+//! same item replaces an earlier one. At the end of an export, the
+//! file is rewritten to contain only the latest entries.
+//!
+//! Two more files record how far an interrupted export got and when
+//! the query has been exported completely the last time.
+
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::http::payload::Item;
+use crate::util::digest;
+
+/// The name of the journal file in the export target.
+pub const STATE_FILE: &str = ".dsc-export.jsonl";
+
+/// The name of the file in the export target that records how far an
+/// export of all items got.
+pub const PROGRESS_FILE: &str = ".dsc-export-progress.json";
+
+/// The name of the file in the export target that records the last
+/// complete export.
+pub const LAST_RUN_FILE: &str = ".dsc-export-last-run.json";
+
+/// An item that has been exported completely.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemEntry {
+    pub id: String,
+    /// The digest of the item metadata, see [`item_digest`].
+    pub digest: String,
+    pub files: Vec<FileEntry>,
+    /// Set if the item has been removed or trashed on the server
+    /// since it was exported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed: Option<String>,
+}
+
+/// A file of an exported item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEntry {
+    /// The attachment id.
+    pub id: String,
+    /// The file name below the item's `files` directory. When
+    /// exporting with a template, this is the path relative to the
+    /// target, using `/` as separator.
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+    /// The modification time of the file in milliseconds since the
+    /// epoch. It is missing in state files of older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<i64>,
+}
+
+impl FileEntry {
+    /// Creates the entry for a file that has just been written, by
+    /// reading its size, modification time and checksum.
+    pub fn from_file(id: String, name: String, path: &Path) -> Result<FileEntry, io::Error> {
+        let meta = std::fs::metadata(path)?;
+        Ok(FileEntry {
+            id,
+            name,
+            size: meta.len(),
+            sha256: digest::digest_file_sha256(path)?,
+            mtime: modified_millis(&meta),
+        })
+    }
+
+    /// Sets the modification time of the file at `path`, after the
+    /// file has been moved or copied there.
+    pub fn update_mtime(&mut self, path: &Path) -> Result<(), io::Error> {
+        self.mtime = modified_millis(&std::fs::metadata(path)?);
+        Ok(())
+    }
+
+    /// Whether the file below `dir` still has the recorded size and
+    /// modification time. With `verify`, or if no modification time
+    /// is recorded, the checksum is compared instead of the time.
+    pub fn is_intact(&self, dir: &Path, verify: bool) -> bool {
+        let file = dir.join(&self.name);
+        match std::fs::metadata(&file) {
+            Ok(meta) if meta.len() == self.size => match self.mtime {
+                Some(mtime) if !verify => modified_millis(&meta) == Some(mtime),
+                _ => digest::digest_file_sha256(&file)
+                    .map(|d| d == self.sha256)
+                    .unwrap_or(false),
+            },
+            _ => false,
+        }
+    }
+}
+
+fn modified_millis(meta: &std::fs::Metadata) -> Option<i64> {
+    let time = meta.modified().ok()?;
+    let millis = time.duration_since(std::time::UNIX_EPOCH).ok()?.as_millis();
+    i64::try_from(millis).ok()
+}
+
+/// How an item compares to the state of a previous export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    New,
+    Changed,
+    Unchanged,
+}
+
+pub struct ExportState {
+    file: PathBuf,
+    items: HashMap<String, ItemEntry>,
+    journal: BufWriter<File>,
+}
+
+impl ExportState {
+    /// Loads the state from the given export target directory. If no
+    /// state file exists, the state is empty.
+    pub fn load(target: &Path) -> Result<ExportState, io::Error> {
+        let file = target.join(STATE_FILE);
+        let mut items = HashMap::new();
+        if file.exists() {
+            let reader = BufReader::new(File::open(&file)?);
+            for line in reader.lines() {
+                let line = line?;
+                // The last line may be incomplete if an export was
+                // interrupted while writing it.
+                match serde_json::from_str::<ItemEntry>(&line) {
+                    Ok(entry) => {
+                        items.insert(entry.id.clone(), entry);
+                    }
+                    Err(err) => log::warn!("Skip invalid state entry '{}': {}", line, err),
+                }
+            }
+            log::debug!("Loaded {} items from {}", items.len(), file.display());
+        } else if !target.exists() {
+            std::fs::create_dir_all(target)?;
+        }
+        let journal = BufWriter::new(OpenOptions::new().create(true).append(true).open(&file)?);
+        Ok(ExportState {
+            file,
+            items,
+            journal,
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ItemEntry> {
+        self.items.get(id)
+    }
+
+    /// Compares the item to its entry in this state. An item is
+    /// unchanged, if its metadata is the same and all its files are
+    /// still intact, see [`FileEntry::is_intact`]. The file names are
+    /// resolved against `files_dir`.
+    pub fn compare(&self, item_digest: &str, files_dir: &Path, id: &str, verify: bool) -> Change {
+        match self.get(id) {
+            None => Change::New,
+            Some(entry) => {
+                let files_ok = entry.files.iter().all(|f| f.is_intact(files_dir, verify));
+                if entry.digest == item_digest && files_ok && entry.removed.is_none() {
+                    Change::Unchanged
+                } else {
+                    Change::Changed
+                }
+            }
+        }
+    }
+
+    /// Records an item as completely exported.
+    pub fn complete(&mut self, entry: ItemEntry) -> Result<(), io::Error> {
+        serde_json::to_writer(&mut self.journal, &entry)?;
+        self.journal.write_all(b"\n")?;
+        self.journal.flush()?;
+        self.items.insert(entry.id.clone(), entry);
+        Ok(())
+    }
+
+    /// Removes an item from the state. This is persisted only with the
+    /// next call to [`ExportState::compact`].
+    pub fn remove(&mut self, id: &str) -> Option<ItemEntry> {
+        self.items.remove(id)
+    }
+
+    /// Returns the ids of all items in this state, sorted.
+    pub fn ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.items.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Rewrites the state file so that it contains a single entry per
+    /// item.
+    pub fn compact(&mut self) -> Result<(), io::Error> {
+        let tmp = self.file.with_extension("jsonl.tmp");
+        {
+            let mut out = BufWriter::new(File::create(&tmp)?);
+            let mut entries: Vec<&ItemEntry> = self.items.values().collect();
+            entries.sort_by(|a, b| a.id.cmp(&b.id));
+            for entry in entries {
+                serde_json::to_writer(&mut out, entry)?;
+                out.write_all(b"\n")?;
+            }
+            out.flush()?;
+        }
+        std::fs::rename(&tmp, &self.file)?;
+        self.journal = BufWriter::new(OpenOptions::new().append(true).open(&self.file)?);
+        Ok(())
+    }
+}
+
+/// How far an export of all items got. When it is interrupted, the
+/// next run with the same query continues at this offset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    pub query: String,
+    pub offset: u32,
+}
+
+impl Progress {
+    /// Loads the progress of an interrupted export from the target
+    /// directory, if there is one.
+    pub fn load(target: &Path) -> Option<Progress> {
+        read_json(&target.join(PROGRESS_FILE))
+    }
+
+    pub fn save(&self, target: &Path) -> Result<(), io::Error> {
+        let json = serde_json::to_vec(self)?;
+        std::fs::write(target.join(PROGRESS_FILE), json)
+    }
+
+    /// Removes the progress file, after an export completed.
+    pub fn clear(target: &Path) -> Result<(), io::Error> {
+        let file = target.join(PROGRESS_FILE);
+        if file.exists() {
+            std::fs::remove_file(file)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The last export of all items of a query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastRun {
+    pub query: String,
+    /// The day the export started, as `YYYY-MM-DD`.
+    pub date: String,
+}
+
+impl LastRun {
+    pub fn new(query: &str, date: NaiveDate) -> LastRun {
+        LastRun {
+            query: query.to_string(),
+            date: date.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// Loads the last export of the given query from the target
+    /// directory. Exports of other queries are ignored.
+    pub fn load(target: &Path, query: &str) -> Option<LastRun> {
+        read_json::<LastRun>(&target.join(LAST_RUN_FILE)).filter(|r| r.query == query)
+    }
+
+    pub fn save(&self, target: &Path) -> Result<(), io::Error> {
+        let json = serde_json::to_vec(self)?;
+        std::fs::write(target.join(LAST_RUN_FILE), json)
+    }
+
+    pub fn date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%Y-%m-%d").ok()
+    }
+}
+
+/// Reads a json file, if it exists and is valid.
+fn read_json<T: DeserializeOwned>(file: &Path) -> Option<T> {
+    let json = std::fs::read_to_string(file).ok()?;
+    serde_json::from_str(&json)
+        .map_err(|err| log::warn!("Skip invalid file {}: {}", file.display(), err))
+        .ok()
+}
+
+/// Computes a digest over the metadata of an item. Search
+/// highlighting is not included, because it depends on the query.
+/// Related items are not included either, because they are not part
+/// of the search results and fetching them for every item would
+/// require another request per item.
+pub fn item_digest(item: &Item) -> Result<String, io::Error> {
+    let mut value = serde_json::to_value(item)?;
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("highlighting");
+        obj.remove("related");
+    }
+    let bytes = serde_json::to_vec(&value)?;
+    digest::digest::<sha2::Sha256, _>(&mut bytes.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::payload::IdName;
+
+    fn entry(id: &str, digest: &str) -> ItemEntry {
+        ItemEntry {
+            id: id.into(),
+            digest: digest.into(),
+            files: vec![],
+            removed: None,
+        }
+    }
+
+    #[test]
+    fn unit_export_state_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("export");
+
+        let mut state = ExportState::load(&target).unwrap();
+        state.complete(entry("item1", "a")).unwrap();
+        state.complete(entry("item2", "b")).unwrap();
+        state.complete(entry("item1", "c")).unwrap();
+        drop(state);
+
+        let mut state = ExportState::load(&target).unwrap();
+        assert_eq!(
+            state.compare("c", &target, "item1", false),
+            Change::Unchanged
+        );
+        assert_eq!(state.compare("x", &target, "item2", false), Change::Changed);
+        assert_eq!(state.compare("a", &target, "item3", false), Change::New);
+
+        state.compact().unwrap();
+        let lines = std::fs::read_to_string(target.join(STATE_FILE)).unwrap();
+        assert_eq!(lines.lines().count(), 2);
+    }
+
+    #[test]
+    fn unit_export_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Progress::load(dir.path()), None);
+        let progress = Progress {
+            query: "tag:invoice".into(),
+            offset: 200,
+        };
+        progress.save(dir.path()).unwrap();
+        assert_eq!(Progress::load(dir.path()), Some(progress));
+        Progress::clear(dir.path()).unwrap();
+        assert_eq!(Progress::load(dir.path()), None);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        LastRun::new("tag:invoice", date).save(dir.path()).unwrap();
+        assert_eq!(LastRun::load(dir.path(), "tag:tax"), None);
+        let last = LastRun::load(dir.path(), "tag:invoice").unwrap();
+        assert_eq!(last.date(), Some(date));
+    }
+
+    #[test]
+    fn unit_item_digest() {
+        let mut item: Item = serde_json::from_str(
+            r#"{"id": "item1", "name": "a", "state": "created", "date": 0, "source": "web",
+                "attachments": [], "tags": [], "customfields": [], "highlighting": []}"#,
+        )
+        .unwrap();
+        let digest = item_digest(&item).unwrap();
+        item.related.push(IdName {
+            id: "item2".into(),
+            name: "b".into(),
+        });
+        assert_eq!(item_digest(&item).unwrap(), digest);
+        item.name = "b".into();
+        assert_ne!(item_digest(&item).unwrap(), digest);
+    }
+}
@@ -0,0 +1,213 @@
+//! Templates for placing exported files into a human-browsable tree.
+//!
+//! A template is a path with placeholders in curly braces, for
+//! example `{year}/{correspondent}/{date}-{name}.{ext}`. It is
+//! rendered for each file of an item. The values are made safe to be
+//! used as file names, so they never create additional directories.
+//! Only `/` in the template itself separates directories.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::cli::table::format_date_by;
+use crate::http::payload::{IdName, Item};
+use crate::util::file;
+
+/// Used for placeholders without a value, like an item without a
+/// correspondent.
+const NO_VALUE: &str = "none";
+
+/// The placeholders that can be used in a template.
+const VARIABLES: [&str; 16] = [
+    "id",
+    "name",
+    "date",
+    "year",
+    "month",
+    "day",
+    "correspondent",
+    "concerning",
+    "folder",
+    "tags",
+    "direction",
+    "state",
+    "source",
+    "file",
+    "ext",
+    "field:<name>",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Var(String),
+    Field(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    parts: Vec<Part>,
+}
+
+impl FromStr for PathTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|n| start + n)
+                .ok_or_else(|| format!("Missing '}}' in template: {}", s))?;
+            let name = &rest[start + 1..end];
+            match name.strip_prefix("field:") {
+                Some(field) if !field.is_empty() => parts.push(Part::Field(field.to_string())),
+                _ if VARIABLES.contains(&name) => parts.push(Part::Var(name.to_string())),
+                _ => {
+                    return Err(format!(
+                        "Unknown placeholder '{{{}}}', use one of: {}",
+                        name,
+                        VARIABLES.join(", ")
+                    ))
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        if parts.is_empty() {
+            return Err("The template must not be empty".into());
+        }
+        Ok(PathTemplate { parts })
+    }
+}
+
+impl PathTemplate {
+    /// Renders the template for a file of the given item. The result
+    /// is a relative path. If the template renders to an empty path,
+    /// like `{ext}` for a file without extension, the file name is
+    /// used instead.
+    pub fn render(&self, item: &Item, file_name: &str) -> PathBuf {
+        let mut path = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => path.push_str(text),
+                // A file without extension gets neither the extension
+                // nor the dot before it.
+                Part::Var(name) if name == "ext" => match variable(name, item, file_name) {
+                    Some(ext) => path.push_str(&safe_value(Some(ext))),
+                    None => {
+                        if path.ends_with('.') {
+                            path.pop();
+                        }
+                    }
+                },
+                Part::Var(name) => path.push_str(&safe_value(variable(name, item, file_name))),
+                Part::Field(name) => {
+                    let value = item
+                        .customfields
+                        .iter()
+                        .find(|f| &f.name == name || f.label.as_ref() == Some(name))
+                        .map(|f| f.value.clone());
+                    path.push_str(&safe_value(value))
+                }
+            }
+        }
+        // Values can't contain a `/`, but the template might create
+        // empty or relative segments.
+        let path: PathBuf = path
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+            .collect();
+        if path.as_os_str().is_empty() {
+            PathBuf::from(safe_value(Some(file_name.to_string())))
+        } else {
+            path
+        }
+    }
+}
+
+fn variable(name: &str, item: &Item, file_name: &str) -> Option<String> {
+    let file = Path::new(file_name);
+    let name_of = |v: &Option<IdName>| v.as_ref().map(|e| e.name.clone());
+    match name {
+        "id" => Some(item.id.clone()),
+        "name" => Some(item.name.clone()),
+        "date" => Some(format_date_by(item.date, "%Y-%m-%d")),
+        "year" => Some(format_date_by(item.date, "%Y")),
+        "month" => Some(format_date_by(item.date, "%m")),
+        "day" => Some(format_date_by(item.date, "%d")),
+        "correspondent" => name_of(&item.corr_org).or_else(|| name_of(&item.corr_person)),
+        "concerning" => name_of(&item.conc_person).or_else(|| name_of(&item.conc_equip)),
+        "folder" => name_of(&item.folder),
+        "tags" => Some(
+            item.tags
+                .iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>()
+                .join("-"),
+        ),
+        "direction" => item.direction.clone(),
+        "state" => Some(item.state.clone()),
+        "source" => Some(item.source.clone()),
+        "file" => file.file_stem().map(|s| s.to_string_lossy().to_string()),
+        "ext" => file.extension().map(|s| s.to_string_lossy().to_string()),
+        _ => None,
+    }
+}
+
+fn safe_value(value: Option<String>) -> String {
+    match value.filter(|v| !v.trim().is_empty()) {
+        Some(v) => file::safe_filename(&v),
+        None => NO_VALUE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_template_render() {
+        let item: Item = serde_json::from_value(serde_json::json!({
+            "id": "abc", "name": "Invoice 1/2", "state": "confirmed",
+            "date": 1640995200000i64, "dueDate": null, "source": "webapp",
+            "direction": "incoming", "corrOrg": {"id": "o1", "name": "ACME"},
+            "corrPerson": null, "concPerson": null, "concEquipment": null,
+            "folder": null, "attachments": [], "tags": [], "notes": null,
+            "customfields": [{"id": "f1", "name": "amount", "label": "Amount",
+                              "ftype": "money", "value": "12.50"}],
+            "highlighting": []
+        }))
+        .unwrap();
+
+        let tpl: PathTemplate = "{year}/{correspondent}/{date}-{name}.{ext}"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            tpl.render(&item, "scan.pdf"),
+            PathBuf::from("2022/ACME/2022-01-01-Invoice 1-2.pdf")
+        );
+        let tpl: PathTemplate = "{folder}/../{field:amount}-{file}".parse().unwrap();
+        assert_eq!(
+            tpl.render(&item, "scan.pdf"),
+            PathBuf::from("none/12.50-scan")
+        );
+        let tpl: PathTemplate = "{date}-{name}.{ext}".parse().unwrap();
+        assert_eq!(
+            tpl.render(&item, "README"),
+            PathBuf::from("2022-01-01-Invoice 1-2")
+        );
+        let tpl: PathTemplate = "{field:Amount}".parse().unwrap();
+        assert_eq!(tpl.render(&item, "scan.pdf"), PathBuf::from("12.50"));
+        let tpl: PathTemplate = "{ext}".parse().unwrap();
+        assert_eq!(tpl.render(&item, "README"), PathBuf::from("README"));
+        assert!("{nope}".parse::<PathTemplate>().is_err());
+        assert!("{year".parse::<PathTemplate>().is_err());
+    }
+}
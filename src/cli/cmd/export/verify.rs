@@ -0,0 +1,76 @@
+use clap::{Parser, ValueHint};
+use snafu::{ResultExt, Snafu};
+use std::path::PathBuf;
+
+use super::manifest::{self, Manifest, MANIFEST_FILE};
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+
+/// Verifies an export against its manifest.
+///
+/// All files listed in the manifest are checked by size and checksum.
+/// Files that are missing or have been changed are reported, as well
+/// as files in the exported directories that are not in the manifest.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// The directory of a previous export.
+    #[arg(short, long, value_hint = ValueHint::DirPath)]
+    target: PathBuf,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+
+    #[snafu(display("Cannot read the manifest {}: {}", path.display(), source))]
+    ReadManifest {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Error verifying {}: {}", path.display(), source))]
+    Verify {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display(
+        "Verification failed: {} missing, {} corrupt and {} extra files",
+        missing,
+        corrupt,
+        extra
+    ))]
+    Failed {
+        missing: usize,
+        corrupt: usize,
+        extra: usize,
+    },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let manifest = Manifest::read(&self.target).context(ReadManifestSnafu {
+            path: self.target.join(MANIFEST_FILE),
+        })?;
+        let result = manifest::verify(&self.target, &manifest).context(VerifySnafu {
+            path: self.target.clone(),
+        })?;
+        let ok = result.ok;
+        let failed = (!result.is_ok()).then_some(Error::Failed {
+            missing: result.missing.len(),
+            corrupt: result.corrupt.len(),
+            extra: result.extra.len(),
+        });
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        match failed {
+            Some(err) => Err(err),
+            None => {
+                eprintln!("All {} files are ok.", ok);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use clap::{Parser, ValueHint};
+use snafu::{ResultExt, Snafu};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::export::REMOVED_FILE;
+use super::upload::{self, PendingItem};
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::payload::{
+    BasicResult, CustomFieldValue, IdName, Item, ItemShort, OptionalDate, OptionalId, OptionalText,
+    StringList, UploadMeta,
+};
+use crate::http::{Error as HttpError, FileAuth};
+use crate::util::digest;
+
+/// Imports items from a directory created by the `export` command.
+///
+/// Each item below the `items` directory is uploaded with all its
+/// files as a single item. After the server has processed it, the
+/// metadata from `metadata.json` is applied: name, dates, notes,
+/// folder, correspondents, tags and custom fields.
+///
+/// Tags that don't exist are created. Folders, correspondents and
+/// custom fields must exist on the server, they are looked up by
+/// name. If they can't be found, a warning is printed and the value
+/// is skipped.
+///
+/// Items whose files are all in one item in Docspell already are not
+/// uploaded again, but the metadata is applied to the existing item.
+/// So an interrupted import can be run again. Items that have been
+/// marked as removed by an export with `--since` are skipped.
+///
+/// This requires an authenticated user.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// How long to wait (in seconds) for the server to process an
+    /// uploaded item.
+    #[arg(long, default_value = "300")]
+    timeout: u64,
+
+    /// Only print which items would be imported, without contacting
+    /// the server.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// The directory of a previous export.
+    #[arg(value_hint = ValueHint::DirPath)]
+    source: PathBuf,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+
+    #[snafu(display("Not an export directory: {}", path.display()))]
+    NotAnExport { path: PathBuf },
+
+    #[snafu(display("Unable to read {}: {}", path.display(), source))]
+    ReadFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Invalid metadata in {}: {}", path.display(), source))]
+    Metadata {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Error creating hash for '{}': {}", path.display(), source))]
+    DigestFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("{}", source))]
+    Upload {
+        #[snafu(source(from(upload::Error, Box::new)))]
+        source: Box<upload::Error>,
+    },
+
+    #[snafu(display("The server rejected {}: {}", path.display(), message))]
+    Rejected { path: PathBuf, message: String },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let items_dir = self.source.join("items");
+        if !items_dir.is_dir() {
+            return Err(Error::NotAnExport {
+                path: self.source.clone(),
+            });
+        }
+
+        let mut tags = Tags::default();
+        let mut imported = 0;
+        let mut updated = 0;
+        let mut skipped = 0;
+        for item_dir in item_dirs(&items_dir)? {
+            match import_item(&item_dir, &mut tags, self, ctx)? {
+                Imported::New => imported += 1,
+                Imported::Existing => updated += 1,
+                Imported::Skipped => skipped += 1,
+            }
+        }
+
+        let result = BasicResult {
+            success: true,
+            message: format!(
+                "Imported {} items, updated {}, skipped {}",
+                imported, updated, skipped
+            ),
+        };
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
+
+/// Returns all item directories in the export, sorted by name. These
+/// are the directories at `items/<xx>/<id>` containing a
+/// `metadata.json` file.
+fn item_dirs(items_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut result = Vec::new();
+    for prefix in read_dir(items_dir)? {
+        if prefix.is_dir() {
+            for dir in read_dir(&prefix)? {
+                if dir.join("metadata.json").is_file() {
+                    result.push(dir);
+                }
+            }
+        }
+    }
+    result.sort();
+    Ok(result)
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let entries = std::fs::read_dir(dir).context(ReadFileSnafu { path: dir })?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        paths.push(entry.context(ReadFileSnafu { path: dir })?.path());
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// How an item has been imported.
+enum Imported {
+    /// The item has been uploaded.
+    New,
+    /// The item was already in Docspell, only its metadata has been
+    /// applied.
+    Existing,
+    Skipped,
+}
+
+/// Imports a single item.
+fn import_item(
+    item_dir: &Path,
+    tags: &mut Tags,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<Imported, Error> {
+    let meta_file = item_dir.join("metadata.json");
+    let content = std::fs::read(&meta_file).context(ReadFileSnafu { path: &meta_file })?;
+    let item: Item =
+        serde_json::from_slice(&content).context(MetadataSnafu { path: &meta_file })?;
+
+    if item_dir.join(REMOVED_FILE).exists() {
+        eprintln!("Skip removed item: {}", item_dir.display());
+        return Ok(Imported::Skipped);
+    }
+    let files = item_files(&item, &item_dir.join("files"))?;
+    if files.is_empty() {
+        eprintln!("Skip item without files: {}", item_dir.display());
+        return Ok(Imported::Skipped);
+    }
+    if opts.dry_run {
+        eprintln!(
+            "Would import {} ({} files): {}",
+            item.name,
+            files.len(),
+            item_dir.display()
+        );
+        return Ok(Imported::New);
+    }
+
+    let fauth = FileAuth::Session {
+        token: ctx.opts.session.clone(),
+    };
+    let (hash, known, existing) = find_existing(&files, &fauth, ctx)?;
+    // The import may have been interrupted after uploading the item,
+    // so the metadata is applied again.
+    if let Some(existing) = existing {
+        eprintln!("Item already in Docspell: {}", item_dir.display());
+        apply_metadata(&existing.id, &item, tags, ctx)?;
+        return Ok(Imported::Existing);
+    }
+
+    eprintln!("Importing {}: {}", item.name, item_dir.display());
+    let meta = UploadMeta {
+        multiple: false,
+        direction: item.direction.clone(),
+        folder: None,
+        skip_duplicates: false,
+        tags: StringList { items: vec![] },
+        file_filter: None,
+        language: None,
+        attachments_only: false,
+        flatten_archives: false,
+    };
+    let paths: Vec<&Path> = files.iter().map(|p| p.as_path()).collect();
+    let result = ctx
+        .client
+        .upload_files(&fauth, &meta, &paths)
+        .context(HttpClientSnafu)?;
+    log::debug!("Upload result: {:?}", result);
+    if !result.success {
+        return Err(Error::Rejected {
+            path: item_dir.to_path_buf(),
+            message: result.message,
+        });
+    }
+
+    let pending = PendingItem { hash, known };
+    let deadline = Instant::now() + Duration::from_secs(opts.timeout);
+    let id =
+        upload::wait_for_item(item_dir, &pending, deadline, &fauth, ctx).context(UploadSnafu)?;
+    log::debug!("Item {} has been created as {}", item.id, id);
+    apply_metadata(&id, &item, tags, ctx)?;
+    Ok(Imported::New)
+}
+
+/// Looks up the files of an item in Docspell. Returns the hash of the
+/// first file, the ids of the items containing it and the newest item
+/// that contains all of the files, if there is one.
+fn find_existing(
+    files: &[PathBuf],
+    fauth: &FileAuth,
+    ctx: &Context,
+) -> Result<(String, HashSet<String>, Option<ItemShort>), Error> {
+    let mut first = None;
+    let mut candidates: Option<Vec<ItemShort>> = None;
+    for file in files {
+        let hash = digest::digest_file_sha256(file).context(DigestFileSnafu { path: file })?;
+        let items = ctx
+            .client
+            .file_exists(&hash, fauth)
+            .context(HttpClientSnafu)?
+            .items;
+        let found = match candidates {
+            None => {
+                first = Some((hash, items.iter().map(|i| i.id.clone()).collect()));
+                items
+            }
+            Some(prev) => prev
+                .into_iter()
+                .filter(|p| items.iter().any(|i| i.id == p.id))
+                .collect(),
+        };
+        let done = found.is_empty();
+        candidates = Some(found);
+        if done {
+            break;
+        }
+    }
+    let (hash, known) = first.expect("Items to import have files");
+    let existing = candidates
+        .unwrap_or_default()
+        .into_iter()
+        .max_by_key(|i| i.created);
+    Ok((hash, known, existing))
+}
+
+/// Returns the files of an item, ordered like its attachments. Files
+/// that can't be matched to an attachment are appended in
+/// alphabetical order.
+fn item_files(item: &Item, files_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !files_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut files: Vec<PathBuf> = read_dir(files_dir)?
+        .into_iter()
+        .filter(|p| p.is_file() && p.extension().map(|e| e != "part").unwrap_or(true))
+        .collect();
+    let position = |path: &PathBuf| {
+        let stem = path.file_stem().and_then(|s| s.to_str());
+        item.attachments
+            .iter()
+            .find(|a| {
+                let name = a.name.as_deref().map(Path::new);
+                name.and_then(|n| n.file_stem()).and_then(|s| s.to_str()) == stem
+            })
+            .map(|a| a.position)
+            .unwrap_or(u32::MAX)
+    };
+    files.sort_by_key(position);
+    Ok(files)
+}
+
+/// Applies the exported metadata to the new item.
+fn apply_metadata(id: &str, item: &Item, tags: &mut Tags, ctx: &Context) -> Result<(), Error> {
+    let token = &ctx.opts.session;
+    let client = &ctx.client;
+    check(client.set_item_name(
+        token,
+        id,
+        &OptionalText {
+            text: Some(item.name.clone()),
+        },
+    ))?;
+    check(client.set_item_date(
+        token,
+        id,
+        &OptionalDate {
+            date: Some(item.date),
+        },
+    ))?;
+    if item.due_date.is_some() {
+        check(client.set_due_date(
+            token,
+            id,
+            &OptionalDate {
+                date: item.due_date,
+            },
+        ))?;
+    }
+    if item.notes.is_some() {
+        check(client.set_item_notes(
+            token,
+            id,
+            &OptionalText {
+                text: item.notes.clone(),
+            },
+        ))?;
+    }
+
+    if let Some(folder) = find(&item.folder, "folder", |n| client.find_folder(token, n))? {
+        check(client.set_folder(token, id, &folder))?;
+    }
+    if let Some(org) = find(&item.corr_org, "organization", |n| {
+        client.find_organization(token, n)
+    })? {
+        check(client.set_corr_org(token, id, &org))?;
+    }
+    if let Some(person) = find(&item.corr_person, "person", |n| {
+        client.find_person(token, n)
+    })? {
+        check(client.set_corr_person(token, id, &person))?;
+    }
+    if let Some(person) = find(&item.conc_person, "person", |n| {
+        client.find_person(token, n)
+    })? {
+        check(client.set_conc_person(token, id, &person))?;
+    }
+    if let Some(equip) = find(&item.conc_equip, "equipment", |n| {
+        client.find_equipment(token, n)
+    })? {
+        check(client.set_conc_equip(token, id, &equip))?;
+    }
+
+    if !item.tags.is_empty() {
+        for tag in &item.tags {
+            tags.ensure(&tag.name, tag.category.as_ref(), ctx)?;
+        }
+        let names = StringList {
+            items: item.tags.iter().map(|t| t.name.clone()).collect(),
+        };
+        check(client.set_tags(token, id, &names))?;
+    }
+
+    for field in &item.customfields {
+        let value = CustomFieldValue {
+            field: field.name.clone(),
+            value: field.value.clone(),
+        };
+        check(client.set_field(token, id, &value))?;
+    }
+    Ok(())
+}
+
+/// Looks up an entity by the name given in the exported metadata.
+fn find<F>(value: &Option<IdName>, kind: &str, lookup: F) -> Result<Option<OptionalId>, Error>
+where
+    F: FnOnce(&str) -> Result<Option<IdName>, HttpError>,
+{
+    match value {
+        None => Ok(None),
+        Some(v) => match lookup(&v.name).context(HttpClientSnafu)? {
+            Some(found) => Ok(Some(OptionalId { id: Some(found.id) })),
+            None => {
+                eprintln!("Warning: {} '{}' not found, skipping it", kind, v.name);
+                Ok(None)
+            }
+        },
+    }
+}
+
+/// Prints a warning if the server rejected an update.
+fn check(result: Result<BasicResult, HttpError>) -> Result<(), Error> {
+    upload::check(result).context(UploadSnafu)
+}
+
+/// The names of all tags known to exist on the server.
+#[derive(Default)]
+struct Tags {
+    names: Option<HashSet<String>>,
+}
+
+impl Tags {
+    /// Creates the tag, if it doesn't exist yet.
+    fn ensure(
+        &mut self,
+        name: &str,
+        category: Option<&String>,
+        ctx: &Context,
+    ) -> Result<(), Error> {
+        if self.names.is_none() {
+            let list = ctx
+                .client
+                .list_tags(&ctx.opts.session, "")
+                .context(HttpClientSnafu)?;
+            self.names = Some(list.items.into_iter().map(|t| t.name).collect());
+        }
+        let names = self.names.get_or_insert_with(HashSet::new);
+        if !names.contains(name) {
+            log::info!("Creating tag {}", name);
+            check(ctx.client.create_tag(&ctx.opts.session, name, category))?;
+            names.insert(name.to_string());
+        }
+        Ok(())
+    }
+}
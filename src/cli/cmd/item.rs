@@ -1,6 +1,10 @@
 pub mod fields;
 pub mod get;
+pub mod link;
+pub mod proposals;
+pub mod related;
 pub mod tags;
+pub mod unlink;
 
 use clap::Parser;
 use snafu::{ResultExt, Snafu};
@@ -24,6 +28,18 @@ pub enum ItemCommand {
 
     #[command(version)]
     Fields(fields::Input),
+
+    #[command(version)]
+    Proposals(proposals::Input),
+
+    #[command(version)]
+    Link(link::Input),
+
+    #[command(version)]
+    Unlink(unlink::Input),
+
+    #[command(version)]
+    Related(related::Input),
 }
 
 #[derive(Debug, Snafu)]
@@ -31,6 +47,10 @@ pub enum Error {
     Get { source: get::Error },
     Tags { source: tags::Error },
     Fields { source: fields::Error },
+    Proposals { source: proposals::Error },
+    Link { source: link::Error },
+    Unlink { source: unlink::Error },
+    Related { source: related::Error },
 }
 
 impl Cmd for Input {
@@ -41,6 +61,10 @@ impl Cmd for Input {
             ItemCommand::Get(input) => input.exec(ctx).context(GetSnafu),
             ItemCommand::Tags(input) => input.exec(ctx).context(TagsSnafu),
             ItemCommand::Fields(input) => input.exec(ctx).context(FieldsSnafu),
+            ItemCommand::Proposals(input) => input.exec(ctx).context(ProposalsSnafu),
+            ItemCommand::Link(input) => input.exec(ctx).context(LinkSnafu),
+            ItemCommand::Unlink(input) => input.exec(ctx).context(UnlinkSnafu),
+            ItemCommand::Related(input) => input.exec(ctx).context(RelatedSnafu),
         }
     }
 }
@@ -41,5 +41,13 @@ fn get_item(id: &str, ctx: &Context) -> Result<ItemDetail, Error> {
         .get_item(&ctx.opts.session, id)
         .context(HttpClientSnafu)?;
 
-    result.ok_or(Error::ItemNotFound)
+    let mut item = result.ok_or(Error::ItemNotFound)?;
+    item.related = ctx
+        .client
+        .get_related_items(&ctx.opts.session, &item.id)
+        .context(HttpClientSnafu)?
+        .into_iter()
+        .map(|i| i.to_idname())
+        .collect();
+    Ok(item)
 }
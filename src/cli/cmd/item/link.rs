@@ -0,0 +1,41 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::Error as HttpError;
+
+/// Relate items to another item.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// The item id (can be abbreviated to a prefix)
+    #[arg(long)]
+    pub id: String,
+
+    /// The ids of the items to relate (can be abbreviated to a
+    /// prefix).
+    #[arg(required = true, num_args = 1)]
+    pub related: Vec<String>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let result = ctx
+            .client
+            .link_items(&ctx.opts.session, &self.id, &self.related)
+            .context(HttpClientSnafu)?;
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
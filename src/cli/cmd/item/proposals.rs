@@ -0,0 +1,255 @@
+use clap::{ArgGroup, Parser};
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::opts::SearchMode;
+use crate::cli::sink::Error as SinkError;
+use crate::http::payload::{
+    BasicResult, IdName, ItemDetail, ItemProposals, ItemProposalsEntry, ItemProposalsList,
+    OptionalDate, OptionalId, SearchReq,
+};
+use crate::http::Error as HttpError;
+
+/// Show or apply the proposals of items.
+///
+/// Docspell computes proposals for correspondents, concerned entities
+/// and dates when processing an item. This command shows them for a
+/// single item (`--id`) or for all items matching a query
+/// (`--query`).
+///
+/// With `--apply-best` the first proposal of each category is set on
+/// the item, but only if the item has no value for it yet. Combined
+/// with a query like `inbox:yes`, this can be used to triage many
+/// items at once. All items matching the query are processed then,
+/// fetching `--limit` items per request.
+#[derive(Parser, Debug)]
+#[command(group = ArgGroup::new("target").required(true))]
+pub struct Input {
+    /// The item id (can be abbreviated to a prefix)
+    #[arg(long, group = "target")]
+    pub id: Option<String>,
+
+    /// A query selecting the items. See
+    /// <https://docspell.org/docs/query/>
+    #[arg(long, group = "target")]
+    pub query: Option<String>,
+
+    #[clap(flatten)]
+    pub search_mode: SearchMode,
+
+    /// Limit the number of results when using `--query`. With
+    /// `--apply-best`, this is the number of items fetched per
+    /// request.
+    #[arg(short, long, default_value = "60")]
+    pub limit: u32,
+
+    /// Skip the first n results when using `--query`.
+    #[arg(short, long, default_value = "0")]
+    pub offset: u32,
+
+    /// Set the best proposal of each category on the item, if the
+    /// item has no value for it.
+    #[arg(long)]
+    pub apply_best: bool,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+
+    #[snafu(display("The item was not found: {}", id))]
+    ItemNotFound { id: String },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        if self.apply_best {
+            let result = apply_proposals(self, ctx)?;
+            ctx.write_result(result).context(WriteResultSnafu)?;
+        } else {
+            match &self.id {
+                Some(id) => {
+                    let result = ctx
+                        .client
+                        .get_item_proposals(&ctx.opts.session, id)
+                        .context(HttpClientSnafu)?;
+                    ctx.write_result(result).context(WriteResultSnafu)?;
+                }
+                None => {
+                    let result = list_proposals(self, ctx)?;
+                    ctx.write_result(result).context(WriteResultSnafu)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns all items selected by either `--id` or `--query`. If
+/// `all_pages` is set, all results of the query are returned starting
+/// at `--offset`, otherwise only one page.
+fn find_items(opts: &Input, all_pages: bool, ctx: &Context) -> Result<Vec<IdName>, Error> {
+    match (&opts.id, &opts.query) {
+        (Some(id), _) => {
+            let item = get_item(id, ctx)?;
+            Ok(vec![IdName {
+                id: item.id,
+                name: item.name,
+            }])
+        }
+        (None, Some(query)) => {
+            let mut req = SearchReq {
+                offset: opts.offset,
+                limit: opts.limit,
+                with_details: false,
+                query: query.clone(),
+                search_mode: opts.search_mode.to_mode(),
+            };
+            let mut items = Vec::new();
+            loop {
+                let results = ctx
+                    .client
+                    .search(&ctx.opts.session, &req)
+                    .context(HttpClientSnafu)?;
+                let page: Vec<IdName> = results
+                    .groups
+                    .into_iter()
+                    .flat_map(|g| g.items)
+                    .map(|i| IdName {
+                        id: i.id,
+                        name: i.name,
+                    })
+                    .collect();
+                let count = page.len();
+                items.extend(page);
+                if !all_pages || count == 0 || count < req.limit as usize {
+                    return Ok(items);
+                }
+                req.offset += req.limit;
+            }
+        }
+        (None, None) => Ok(vec![]),
+    }
+}
+
+fn get_item(id: &str, ctx: &Context) -> Result<ItemDetail, Error> {
+    ctx.client
+        .get_item(&ctx.opts.session, id)
+        .context(HttpClientSnafu)?
+        .ok_or_else(|| Error::ItemNotFound { id: id.to_string() })
+}
+
+fn list_proposals(opts: &Input, ctx: &Context) -> Result<ItemProposalsList, Error> {
+    let mut items = Vec::new();
+    for item in find_items(opts, false, ctx)? {
+        let proposals = ctx
+            .client
+            .get_item_proposals(&ctx.opts.session, &item.id)
+            .context(HttpClientSnafu)?;
+        items.push(ItemProposalsEntry {
+            id: item.id,
+            name: item.name,
+            proposals,
+        });
+    }
+    Ok(ItemProposalsList { items })
+}
+
+fn apply_proposals(opts: &Input, ctx: &Context) -> Result<BasicResult, Error> {
+    // collect all items first, applying proposals may change the
+    // results of the query
+    let items = find_items(opts, true, ctx)?;
+    let mut counter = 0;
+    for idname in &items {
+        let item = get_item(&idname.id, ctx)?;
+        let proposals = ctx
+            .client
+            .get_item_proposals(&ctx.opts.session, &item.id)
+            .context(HttpClientSnafu)?;
+        let applied = apply_best(&item, &proposals, ctx)?;
+        if applied.is_empty() {
+            eprintln!("Nothing to apply for: {}", item.name);
+        } else {
+            counter += 1;
+            eprintln!("Applied {} for: {}", applied.join(", "), item.name);
+        }
+    }
+    Ok(BasicResult {
+        success: true,
+        message: format!(
+            "Applied proposals to {} of {} item(s)",
+            counter,
+            items.len()
+        ),
+    })
+}
+
+/// Sets the first proposal of each category that has no value on the
+/// item. Returns the names of the categories that have been set.
+fn apply_best(
+    item: &ItemDetail,
+    proposals: &ItemProposals,
+    ctx: &Context,
+) -> Result<Vec<&'static str>, Error> {
+    let token = &ctx.opts.session;
+    let client = &ctx.client;
+    let mut applied = Vec::new();
+
+    if let (None, Some(org)) = (&item.corr_org, proposals.corr_org.first()) {
+        let res = client
+            .set_corr_org(token, &item.id, &optional_id(&org.id))
+            .context(HttpClientSnafu)?;
+        check_applied(res, "correspondent organization", &mut applied);
+    }
+    if let (None, Some(person)) = (&item.corr_person, proposals.corr_person.first()) {
+        let res = client
+            .set_corr_person(token, &item.id, &optional_id(&person.id))
+            .context(HttpClientSnafu)?;
+        check_applied(res, "correspondent person", &mut applied);
+    }
+    if let (None, Some(person)) = (&item.conc_person, proposals.conc_person.first()) {
+        let res = client
+            .set_conc_person(token, &item.id, &optional_id(&person.id))
+            .context(HttpClientSnafu)?;
+        check_applied(res, "concerning person", &mut applied);
+    }
+    if let (None, Some(equip)) = (&item.conc_equip, proposals.conc_equip.first()) {
+        let res = client
+            .set_conc_equip(token, &item.id, &optional_id(&equip.id))
+            .context(HttpClientSnafu)?;
+        check_applied(res, "concerning equipment", &mut applied);
+    }
+    if let (None, Some(date)) = (&item.item_date, proposals.item_date.first()) {
+        let res = client
+            .set_item_date(token, &item.id, &OptionalDate { date: Some(*date) })
+            .context(HttpClientSnafu)?;
+        check_applied(res, "item date", &mut applied);
+    }
+    if let (None, Some(date)) = (&item.due_date, proposals.due_date.first()) {
+        let res = client
+            .set_due_date(token, &item.id, &OptionalDate { date: Some(*date) })
+            .context(HttpClientSnafu)?;
+        check_applied(res, "due date", &mut applied);
+    }
+    Ok(applied)
+}
+
+fn optional_id(id: &str) -> OptionalId {
+    OptionalId {
+        id: Some(id.to_string()),
+    }
+}
+
+fn check_applied(result: BasicResult, category: &'static str, applied: &mut Vec<&'static str>) {
+    if result.success {
+        applied.push(category);
+    } else {
+        eprintln!("Setting {} failed: {}", category, result.message);
+    }
+}
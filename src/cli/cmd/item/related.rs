@@ -0,0 +1,43 @@
+use clap::Parser;
+use snafu::{ResultExt, Snafu};
+
+use super::{Cmd, Context};
+use crate::cli::sink::Error as SinkError;
+use crate::http::payload::{Group, SearchResult};
+use crate::http::Error as HttpError;
+
+/// Lists all items related to an item.
+#[derive(Parser, Debug)]
+pub struct Input {
+    /// The item id (can be abbreviated to a prefix)
+    #[arg(long)]
+    pub id: String,
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("An http error occurred: {}", source))]
+    HttpClient { source: HttpError },
+
+    #[snafu(display("Error writing data: {}", source))]
+    WriteResult { source: SinkError },
+}
+
+impl Cmd for Input {
+    type CmdError = Error;
+
+    fn exec(&self, ctx: &Context) -> Result<(), Error> {
+        let items = ctx
+            .client
+            .get_related_items(&ctx.opts.session, &self.id)
+            .context(HttpClientSnafu)?;
+        let result = SearchResult {
+            groups: vec![Group {
+                name: "related".into(),
+                items,
+            }],
+        };
+        ctx.write_result(result).context(WriteResultSnafu)?;
+        Ok(())
+    }
+}
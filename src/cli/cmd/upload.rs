@@ -1,15 +1,39 @@
+pub mod dirconfig;
+pub mod hooks;
+pub mod sidecar;
+
 use clap::{ArgAction, ArgGroup, Parser, ValueHint};
 use snafu::{ResultExt, Snafu};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use self::dirconfig::DirConfigs;
+use self::hooks::Hook;
+use self::sidecar::Sidecar;
 use super::{Cmd, Context};
-use crate::cli::opts::{EndpointOpts, FileAction, FileAuthError, UploadMeta};
+use crate::cli::opts::{ActionSpec, EndpointOpts, FileAction, FileAuthError, UploadMeta};
 use crate::cli::sink::Error as SinkError;
 use crate::http::payload::{BasicResult, StringList, UploadMeta as MetaRequest};
-use crate::http::{Error as HttpError, FileAuth};
+use crate::http::retry::Transient;
+use crate::http::{Client, Error as HttpError, FileAuth};
 use crate::util::file::FileActionResult;
 use crate::util::{digest, file};
 
+/// How long to wait between checking whether an uploaded file has
+/// been processed.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The extension appended to a failed file to get the name of its
+/// error report.
+const ERROR_REPORT_EXT: &str = "error.txt";
+
+/// Http status codes of responses that reject the uploaded file, like
+/// `413 Payload Too Large` and `415 Unsupported Media Type`.
+const FILE_STATUS: [u16; 2] = [413, 415];
+
 /// Uploads files to docspell.
 ///
 /// To upload a file, an authenticated user is required, a source id
@@ -25,10 +49,36 @@ use crate::util::{digest, file};
 /// patters for inclusion/exclusion which apply to both modes. These
 /// patterns are matched against the complete filename (with path).
 ///
-/// Successfully uploaded files can be deleted or moved to another
-/// directory. If a file already exists it is also deleted or moved.
-/// So using `upload --traverse --delete` will upload all files that
-/// are not yet in Docspell and then deletes them.
+/// When traversing directories, a file `.dsc.toml` in a directory
+/// can specify tags, folder, direction, language and source for all
+/// files below it. They are used for the options not given here,
+/// tags are added. Files in subdirectories inherit the settings of
+/// their parent directories.
+///
+/// A file `<name>.json` next to a file to upload, like
+/// `scan001.pdf.json`, can specify metadata for the new item. Tags,
+/// folder, direction and language are sent with the upload. Name,
+/// date, due date, correspondent, notes and custom fields are set
+/// after the server has processed the file, which requires to be
+/// logged in. Such sidecar files are never uploaded themselves and
+/// are deleted or moved together with their file. They are not used
+/// with `--single-item`.
+///
+/// Files that failed to upload can be moved elsewhere with
+/// `--on-error`, together with a report of the error.
+///
+/// Commands configured as `pre_upload_hook`, `post_upload_hook`,
+/// `on_duplicate_hook` and `on_error_hook` in the config file are run
+/// for each file. They get the file path and the result as
+/// environment variables and as JSON on stdin. A failing pre-upload
+/// hook counts as a failed upload, failures of the others are only
+/// printed. Hooks are not run with `--dry-run`.
+///
+/// Successfully uploaded files can be deleted, moved or copied to
+/// another directory. If a file already exists the same is done,
+/// unless a different action is given with `--on-duplicate`. So using
+/// `upload --traverse --delete` will upload all files that are not
+/// yet in Docspell and then deletes them.
 ///
 /// For glob patterns, see <https://docs.rs/glob/0.3.0/glob/struct.Pattern.html>
 #[derive(Parser, Debug)]
@@ -73,6 +123,34 @@ pub struct Input {
     #[arg(long)]
     pub poll: Option<u64>,
 
+    /// Can be used with `--traverse` to digest, check and upload this
+    /// many files concurrently. Files are moved or deleted only after
+    /// they have been uploaded successfully.
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u16).range(1..))]
+    pub parallel: u16,
+
+    /// The action for files that failed to upload: `keep`, `delete`,
+    /// `move:<dir>` or `copy:<dir>`. When moved or copied, an error
+    /// report `<file>.error.txt` is written next to the file. This is
+    /// done for files the server rejected, also with status 413 or
+    /// 415, and files whose sidecar file or pre-upload hook failed.
+    /// With `--traverse`, failed files are counted and the upload
+    /// continues with the next file. Otherwise the upload stops at the
+    /// first error. If the server can't be reached, doesn't answer in
+    /// time or responds with a server error, the upload stops, too, or
+    /// is retried with the next `--poll` until `--max-attempts` is
+    /// reached. Other errors, like invalid credentials or an invalid
+    /// directory config, always stop the upload.
+    #[arg(long, value_name = "ACTION")]
+    pub on_error: Option<ActionSpec>,
+
+    /// How often a file is attempted, if it fails because the server
+    /// can't be reached, doesn't answer in time or responds with a
+    /// server error, before the `--on-error` action is applied to it.
+    /// Each `--poll` is one attempt.
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_attempts: u32,
+
     /// Doesn't submit the request, but prints which files would be
     /// uploaded instead. This might be useful when using `--traverse`
     /// and glob patterns.
@@ -134,11 +212,109 @@ pub enum Error {
     #[snafu(display("The `--poll` option requires `--traverse`"))]
     PollWithoutTraverse,
 
+    #[snafu(display("The `--parallel` option requires `--traverse`"))]
+    ParallelWithoutTraverse,
+
     #[snafu(display("The glob pattern '{}' is invalid: {}", pattern, source))]
     BadGlobPattern {
         source: glob::PatternError,
         pattern: String,
     },
+
+    #[snafu(display("Unable to read sidecar file {}: {}", path.display(), source))]
+    SidecarRead {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Unable to parse sidecar file {}: {}", path.display(), source))]
+    SidecarParse {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Invalid sidecar file {}: {}", path.display(), message))]
+    SidecarInvalid { path: PathBuf, message: String },
+
+    #[snafu(display("Unable to write error report {}: {}", path.display(), source))]
+    ErrorReport {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("{} was not processed within the process timeout", path.display()))]
+    ProcessTimeout { path: PathBuf },
+
+    #[snafu(display("{} has been uploaded, but: {}", path.display(), source))]
+    AfterUpload {
+        #[snafu(source(from(Error, Box::new)))]
+        source: Box<Error>,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Unable to read directory config {}: {}", path.display(), source))]
+    DirConfigRead {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Unable to parse directory config {}: {}", path.display(), source))]
+    DirConfigParse {
+        source: toml::de::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Unable to run the {} hook: {}", hook, source))]
+    HookRun {
+        source: std::io::Error,
+        hook: String,
+    },
+
+    #[snafu(display("The {} hook failed for {}: {}", hook, path.display(), status))]
+    HookFailed {
+        hook: String,
+        path: PathBuf,
+        status: String,
+    },
+}
+
+/// How an error affects the upload of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The server could not be reached, didn't answer in time or
+    /// responded with a server error. Uploading the file again later
+    /// may succeed.
+    Transient,
+    /// The file can't be uploaded, for example because the server
+    /// rejected it or its sidecar file is invalid. Other files are not
+    /// affected.
+    File,
+    /// An error that would happen for every file, like invalid
+    /// credentials or configuration. The upload must stop.
+    Fatal,
+}
+
+impl Error {
+    /// Returns how the error affects the upload of the file. Only http
+    /// errors that clearly concern the file make it fail, other ones,
+    /// like an expired session or an unknown source id, are fatal.
+    pub fn kind(&self, client: &Client) -> ErrorKind {
+        match self {
+            Error::HttpClient { source } if client.is_transient(source) => ErrorKind::Transient,
+            Error::HttpClient { source } => match source.status() {
+                Some(status) if FILE_STATUS.contains(&status) => ErrorKind::File,
+                _ => ErrorKind::Fatal,
+            },
+            Error::OpenFile { .. }
+            | Error::DigestFile { .. }
+            | Error::SidecarRead { .. }
+            | Error::SidecarParse { .. }
+            | Error::SidecarInvalid { .. }
+            | Error::HookFailed { .. }
+            | Error::AfterUpload { .. } => ErrorKind::File,
+            _ => ErrorKind::Fatal,
+        }
+    }
 }
 
 impl Cmd for Input {
@@ -153,26 +329,8 @@ impl Cmd for Input {
 
 pub fn upload_files(args: &Input, ctx: &Context) -> Result<BasicResult, Error> {
     check_flags(args)?;
-    let matcher = matching::Matcher::new(args)?;
+    let matcher = matching::Matcher::new(&args.matches, &args.not_matches)?;
 
-    let meta = MetaRequest {
-        multiple: args.multiple,
-        direction: args
-            .upload
-            .direction
-            .clone()
-            .map(|d| d.to_value().to_string()),
-        folder: args.upload.folder.clone(),
-        skip_duplicates: args.upload.skip_duplicates,
-        tags: StringList {
-            items: args.upload.tag.clone(),
-        },
-        file_filter: args.upload.file_filter.clone(),
-        language: args.upload.language.clone(),
-        attachments_only: args.upload.attachments_only,
-        flatten_archives: args.upload.flatten_archives,
-    };
-    log::debug!("Send file metadata: {:?}", serde_json::to_string(&meta));
     if args.traverse {
         if let Some(delay) = args.poll {
             let delay_dur = std::time::Duration::from_secs(delay);
@@ -182,27 +340,81 @@ pub fn upload_files(args: &Input, ctx: &Context) -> Result<BasicResult, Error> {
                 .map(|p| p.display().to_string())
                 .collect::<Vec<String>>()
                 .join(", ");
+            let attempts = Attempts::default();
             loop {
                 eprintln!(
                     "Traversing to upload '{}' (every {:?}) …",
                     dir_list, delay_dur
                 );
-                upload_traverse(&meta, args, ctx, &matcher)?;
+                match upload_traverse(args, ctx, &matcher, &attempts) {
+                    // Connection problems and server errors are retried
+                    // with the next poll
+                    Err(err) if err.kind(&ctx.client) == ErrorKind::Transient => {
+                        log::error!("Uploading failed: {}", err);
+                        eprintln!("Upload failed, retrying with the next poll: {}", err);
+                    }
+                    Err(err @ Error::AfterUpload { .. }) => {
+                        log::error!("{}", err);
+                        eprintln!("Error: {}", err);
+                    }
+                    res => {
+                        res?;
+                    }
+                }
                 std::thread::sleep(delay_dur);
             }
         } else {
-            upload_traverse(&meta, args, ctx, &matcher)
+            upload_traverse(args, ctx, &matcher, &Attempts::default())
         }
     } else {
+        let meta = upload_meta(args.multiple, &args.upload);
         upload_single(&meta, args, ctx, matcher)
     }
 }
 
-fn apply_file_action(path: &Path, root: Option<&PathBuf>, opts: &Input) -> Result<(), Error> {
-    let res = opts
-        .action
-        .execute(path, root)
-        .context(FileActionSnafu { path })?;
+fn upload_meta(multiple: bool, upload: &UploadMeta) -> MetaRequest {
+    let meta = MetaRequest {
+        multiple,
+        direction: upload.direction.clone().map(|d| d.to_value().to_string()),
+        folder: upload.folder.clone(),
+        skip_duplicates: upload.skip_duplicates,
+        tags: StringList {
+            items: upload.tag.clone(),
+        },
+        file_filter: upload.file_filter.clone(),
+        language: upload.language.clone(),
+        attachments_only: upload.attachments_only,
+        flatten_archives: upload.flatten_archives,
+    };
+    log::debug!("Send file metadata: {:?}", serde_json::to_string(&meta));
+    meta
+}
+
+/// Runs the post-upload or on-duplicate hook and applies the file
+/// action to the file and its sidecar file, if present.
+fn apply_file_action(
+    path: &Path,
+    root: Option<&PathBuf>,
+    outcome: &Outcome,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    if !opts.dry_run {
+        match outcome {
+            Outcome::Uploaded(id) => {
+                hooks::notify(Hook::PostUpload, path, id.as_deref(), None, ctx)
+            }
+            Outcome::Duplicate(id) => {
+                hooks::notify(Hook::OnDuplicate, path, id.as_deref(), None, ctx)
+            }
+        }
+    }
+    let res = match outcome {
+        Outcome::Uploaded(id) => opts.action.execute(path, root, id.as_deref()),
+        Outcome::Duplicate(id) => opts.action.execute_duplicate(path, root, id.as_deref()),
+    }
+    .context(FileActionSnafu { path })?;
+    follow_sidecar(path, root, &res)?;
     match res {
         FileActionResult::Deleted(_p) => {
             eprintln!("Deleted file");
@@ -212,18 +424,151 @@ fn apply_file_action(path: &Path, root: Option<&PathBuf>, opts: &Input) -> Resul
             eprintln!("Moved file to: {}", p.display());
             Ok(())
         }
+        FileActionResult::Copied(p) => {
+            eprintln!("Copied file to: {}", p.display());
+            Ok(())
+        }
         FileActionResult::Nothing => Ok(()),
     }
 }
 
+/// Applies the result of the file action to the sidecar file, so it
+/// stays next to its file. An existing file at the target is not
+/// overwritten.
+fn follow_sidecar(
+    path: &Path,
+    root: Option<&PathBuf>,
+    res: &FileActionResult,
+) -> Result<(), Error> {
+    let sidecar = Sidecar::path_for(path);
+    if !sidecar.is_file() {
+        return Ok(());
+    }
+    let target = |p: &Path| {
+        let target = Sidecar::path_for(p);
+        if target.exists() {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} already exists", target.display()),
+            ))
+        } else {
+            Ok(target)
+        }
+    };
+    match res {
+        FileActionResult::Deleted(_) => std::fs::remove_file(&sidecar),
+        FileActionResult::Moved(p) => target(p).and_then(|t| std::fs::rename(&sidecar, t)),
+        FileActionResult::Copied(p) => target(p).and_then(|t| std::fs::copy(&sidecar, t).map(drop)),
+        FileActionResult::Nothing => return Ok(()),
+    }
+    .and_then(|_| file::delete_parent_if_empty(&sidecar, root))
+    .context(FileActionSnafu { path: &sidecar })
+}
+
+/// Returns the items that contain the file before it is uploaded, if
+/// the id of the new item is needed afterwards. This must be called
+/// before uploading the file.
+fn pending_item(
+    path: &Path,
+    sidecar: Option<&Sidecar>,
+    fauth: &FileAuth,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<Option<PendingItem>, Error> {
+    let needs_id =
+        opts.action.needs_item_id() || sidecar.map(Sidecar::has_updates).unwrap_or(false);
+    if !needs_id {
+        return Ok(None);
+    }
+    let hash = digest::digest_file_sha256(path).context(DigestFileSnafu { path })?;
+    let known = ctx
+        .client
+        .file_exists(&hash, fauth)
+        .context(HttpClientSnafu)?
+        .items
+        .into_iter()
+        .map(|item| item.id)
+        .collect();
+    Ok(Some(PendingItem { hash, known }))
+}
+
+/// Applies the values of the sidecar file and the file action after
+/// a file has been uploaded. If the id of the new item is needed for
+/// this, it waits until the server has processed the file.
+fn after_upload(
+    path: &Path,
+    root: Option<&PathBuf>,
+    sidecar: Option<&Sidecar>,
+    pending: Option<PendingItem>,
+    fauth: &FileAuth,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    let id = match pending {
+        Some(pending) => Some(wait_for_item(path, &pending, deadline(opts), fauth, ctx)?),
+        None => None,
+    };
+    if let (Some(sc), Some(id)) = (sidecar, &id) {
+        sc.apply(id, ctx)?;
+    }
+    apply_file_action(path, root, &Outcome::Uploaded(id), opts, ctx)
+}
+
+/// Returns until when to wait for uploaded files to be processed.
+fn deadline(opts: &Input) -> Instant {
+    Instant::now() + Duration::from_secs(opts.action.process_timeout)
+}
+
+/// Waits until the server has processed the uploaded file and returns
+/// the id of the new item. Items that contained the file before the
+/// upload are ignored. If there are still several, the newest one is
+/// used.
+pub fn wait_for_item(
+    path: &Path,
+    pending: &PendingItem,
+    deadline: Instant,
+    fauth: &FileAuth,
+    ctx: &Context,
+) -> Result<String, Error> {
+    loop {
+        let result = ctx
+            .client
+            .file_exists(&pending.hash, fauth)
+            .context(HttpClientSnafu)?;
+        let item = result
+            .items
+            .into_iter()
+            .filter(|item| !pending.known.contains(&item.id))
+            .max_by_key(|item| item.created);
+        if let Some(item) = item {
+            return Ok(item.id);
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::ProcessTimeout {
+                path: path.to_path_buf(),
+            });
+        }
+        log::debug!("Waiting for {} to be processed…", path.display());
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Prints a warning if the server rejected an update of an item.
+pub fn check(result: Result<BasicResult, HttpError>) -> Result<(), Error> {
+    let result = result.context(HttpClientSnafu)?;
+    if !result.success {
+        eprintln!("Warning: {}", result.message);
+    }
+    Ok(())
+}
+
 fn upload_traverse(
-    meta: &MetaRequest,
     opts: &Input,
     ctx: &Context,
     matcher: &matching::Matcher,
+    attempts: &Attempts,
 ) -> Result<BasicResult, Error> {
     log::debug!("Upload by traversing directory");
-    let mut counter = 0;
     for path in &opts.files {
         if !path.exists() {
             return Err(Error::FileMissing {
@@ -231,75 +576,271 @@ fn upload_traverse(
             });
         }
     }
+    let mut jobs = Vec::new();
     for path in &opts.files {
         if path.is_dir() {
             for child in matcher.traverse(path)? {
-                if !child.is_dir() {
-                    let fauth = opts
-                        .endpoint
-                        .to_file_auth(ctx, &|| {
-                            file::collective_from_subdir(&child, &[path.to_path_buf()])
-                                .unwrap_or(None)
-                        })
-                        .context(CredentialsReadSnafu {
-                            path: child.clone(),
-                        })?;
-                    let exists = check_existence(&child, opts, ctx, &fauth)?;
-                    if !exists {
-                        eprintln!("Uploading {}", child.display());
-                        counter += 1;
-                        if !opts.dry_run {
-                            let res = ctx
-                                .client
-                                .upload_files(&fauth, meta, &[child.as_path()])
-                                .context(HttpClientSnafu)?;
-                            if res.success {
-                                apply_file_action(&child, Some(path), opts)?;
-                            }
-                        }
-                    } else {
-                        file_exists_message(&child);
-                        apply_file_action(&child, Some(path), opts)?;
-                    }
+                if !child.is_dir()
+                    && !dirconfig::is_config_file(&child)
+                    && !sidecar::is_sidecar(&child)
+                    && !is_error_report(&child)
+                {
+                    jobs.push(UploadJob {
+                        file: child,
+                        root: Some(path.clone()),
+                    });
                 }
             }
         } else if matcher.is_included(path) {
             log::debug!("Uploading given regular file: {:?}", path);
-            let fauth = opts
-                .endpoint
-                .to_file_auth(ctx, &|| None)
-                .context(CredentialsReadSnafu { path: path.clone() })?;
-            let exists = check_existence(path, opts, ctx, &fauth)?;
-            if !exists {
-                eprintln!("Uploading file {}", path.display());
-                counter += 1;
-                if !opts.dry_run {
-                    let res = ctx
-                        .client
-                        .upload_files(&fauth, meta, &[path.as_path()])
-                        .context(HttpClientSnafu)?;
-                    if res.success {
-                        apply_file_action(path, None, opts)?;
+            jobs.push(UploadJob {
+                file: path.clone(),
+                root: None,
+            });
+        }
+    }
+
+    let configs = DirConfigs::default();
+    let digests = Digests::default();
+    let results = if opts.parallel > 1 && jobs.len() > 1 {
+        upload_parallel(&jobs, &configs, &digests, attempts, opts, ctx)?
+    } else {
+        let mut results = Vec::new();
+        for job in &jobs {
+            results.push(upload_job(job, &configs, &digests, attempts, opts, ctx)?);
+        }
+        results
+    };
+
+    let uploaded = results
+        .iter()
+        .filter(|r| **r == JobResult::Uploaded)
+        .count();
+    let failed = results.iter().filter(|r| **r == JobResult::Failed).count();
+    if failed > 0 {
+        Ok(BasicResult {
+            success: false,
+            message: format!("Uploaded {}, failed {}", uploaded, failed),
+        })
+    } else {
+        Ok(BasicResult {
+            success: true,
+            message: format!("Uploaded {}", uploaded),
+        })
+    }
+}
+
+/// Runs the upload jobs using `--parallel` worker threads. Each worker
+/// takes the next job from the list until all are done. When a job
+/// fails, the remaining jobs are not started and the error of the
+/// first failed job (in traversal order) is returned.
+fn upload_parallel(
+    jobs: &[UploadJob],
+    configs: &DirConfigs,
+    digests: &Digests,
+    attempts: &Attempts,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<Vec<JobResult>, Error> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let workers = usize::from(opts.parallel).min(jobs.len());
+    log::debug!("Uploading {} files with {} workers", jobs.len(), workers);
+
+    let mut results: Vec<(usize, Result<JobResult, Error>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    while !failed.load(Ordering::SeqCst) {
+                        let idx = next.fetch_add(1, Ordering::SeqCst);
+                        match jobs.get(idx) {
+                            Some(job) => {
+                                let res = upload_job(job, configs, digests, attempts, opts, ctx);
+                                if res.is_err() {
+                                    failed.store(true, Ordering::SeqCst);
+                                }
+                                results.push((idx, res));
+                            }
+                            None => break,
+                        }
                     }
-                }
+                    results
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("Upload worker panicked"))
+            .collect()
+    });
+
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, res)| res).collect()
+}
+
+/// Uploads a single file like `try_upload_job` and handles errors by
+/// their [`ErrorKind`]. Transient errors are returned, so that the
+/// upload stops or is retried with the next poll, until the file has
+/// been attempted `--max-attempts` times. For other errors the error
+/// hook is run. If `--on-error` is given, the error action is applied
+/// to a file that can't be uploaded instead of returning the error.
+/// This is not done for files that have been uploaded. A failing
+/// pre-upload hook or an error after the file has been uploaded only
+/// fails this file. All other errors stop the upload.
+fn upload_job(
+    job: &UploadJob,
+    configs: &DirConfigs,
+    digests: &Digests,
+    attempts: &Attempts,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<JobResult, Error> {
+    match try_upload_job(job, configs, digests, opts, ctx) {
+        Ok(res) => {
+            attempts.clear(&job.file);
+            Ok(res)
+        }
+        Err(err) => {
+            let kind = err.kind(&ctx.client);
+            let gave_up = kind == ErrorKind::Transient
+                && opts.on_error.is_some()
+                && attempts.failed(&job.file) >= opts.max_attempts;
+            if kind == ErrorKind::Transient && !gave_up {
+                return Err(err);
+            }
+            let uploaded = matches!(err, Error::AfterUpload { .. });
+            let spec = opts
+                .on_error
+                .as_ref()
+                .filter(|_| gave_up || (kind == ErrorKind::File && !uploaded));
+            let failed = spec.is_some() || uploaded || matches!(err, Error::HookFailed { .. });
+            let message = if gave_up {
+                format!("Giving up after {} attempts: {}", opts.max_attempts, err)
             } else {
-                file_exists_message(path);
-                apply_file_action(path, None, opts)?;
+                err.to_string()
+            };
+            if failed {
+                eprintln!("Uploading {} failed: {}", job.file.display(), message);
+            }
+            handle_failure(&job.file, job.root.as_ref(), &message, spec, opts, ctx)?;
+            if failed {
+                Ok(JobResult::Failed)
+            } else {
+                Err(err)
             }
         }
     }
+}
 
-    Ok(BasicResult {
-        success: true,
-        message: format!("Uploaded {}", counter),
-    })
+/// Uploads a single file, unless it already exists in Docspell, and
+/// applies the file action. Files with the same contents are checked
+/// and uploaded one after another, so only the first one is uploaded.
+fn try_upload_job(
+    job: &UploadJob,
+    configs: &DirConfigs,
+    digests: &Digests,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<JobResult, Error> {
+    let path = &job.file;
+    if !opts.dry_run {
+        hooks::run(Hook::PreUpload, path, None, None, ctx)?;
+    }
+    let mut upload = opts.upload.clone();
+    let mut endpoint = opts.endpoint.clone();
+    configs
+        .resolve(path, job.root.as_deref())?
+        .apply(&mut upload, &mut endpoint);
+    let sidecar = Sidecar::read(path)?;
+    if let Some(sc) = &sidecar {
+        sc.upload_settings()
+            .apply_override(&mut upload, &mut endpoint);
+    }
+    let meta = &upload_meta(opts.multiple, &upload);
+    let fauth = endpoint
+        .to_file_auth(ctx, &|| match &job.root {
+            Some(root) => {
+                file::collective_from_subdir(path, std::slice::from_ref(root)).unwrap_or(None)
+            }
+            None => None,
+        })
+        .context(CredentialsReadSnafu { path: path.clone() })?;
+    let digest = if opts.upload.skip_duplicates {
+        Some(digest::digest_file_sha256(path).context(DigestFileSnafu { path })?)
+    } else {
+        None
+    };
+    let slot = digest.as_deref().map(|d| digests.slot(d));
+    let mut uploaded = slot
+        .as_ref()
+        .map(|s| s.lock().expect("Digest lock poisoned"));
+    let existing = match (&uploaded, &digest) {
+        (Some(done), _) if **done => Some(Outcome::Duplicate(None)),
+        (_, Some(hash)) => find_existing(hash, &fauth, ctx)?,
+        _ => None,
+    };
+    match existing {
+        None => {
+            eprintln!("Uploading {}", path.display());
+            if !opts.dry_run {
+                let pending = pending_item(path, sidecar.as_ref(), &fauth, opts, ctx)?;
+                let res = ctx
+                    .client
+                    .upload_files(&fauth, meta, &[path.as_path()])
+                    .context(HttpClientSnafu)?;
+                if res.success {
+                    if let Some(done) = uploaded.as_mut() {
+                        **done = true;
+                    }
+                    let sc = sidecar.as_ref();
+                    after_upload(path, job.root.as_ref(), sc, pending, &fauth, opts, ctx)
+                        .context(AfterUploadSnafu { path })?;
+                } else {
+                    eprintln!("Server rejected {}: {}", path.display(), res.message);
+                    let spec = opts.on_error.as_ref();
+                    handle_failure(path, job.root.as_ref(), &res.message, spec, opts, ctx)?;
+                    return Ok(JobResult::Failed);
+                }
+            }
+            Ok(JobResult::Uploaded)
+        }
+        Some(dupe) => {
+            file_exists_message(path);
+            apply_file_action(path, job.root.as_ref(), &dupe, opts, ctx)?;
+            Ok(JobResult::Skipped)
+        }
+    }
+}
+
+/// Checks whether the file is already in Docspell, unless duplicates
+/// are allowed. Such a file is only skipped, the file action is not
+/// applied to it.
+pub fn skip_existing(path: &Path, opts: &Input, ctx: &Context) -> Result<bool, Error> {
+    if !opts.upload.skip_duplicates {
+        return Ok(false);
+    }
+    let fauth = opts
+        .endpoint
+        .to_file_auth(ctx, &|| None)
+        .context(CredentialsReadSnafu { path })?;
+    let hash = digest::digest_file_sha256(path).context(DigestFileSnafu { path })?;
+    let result = ctx
+        .client
+        .file_exists(hash, &fauth)
+        .context(HttpClientSnafu)?;
+    if result.exists {
+        file_exists_message(path);
+    }
+    Ok(result.exists)
 }
 
 fn file_exists_message(path: &Path) {
     eprintln!("File already in Docspell: {}", path.display());
 }
 
-/// Uploads all files in a single request.
+/// Uploads all files in a single request. Files with a sidecar file
+/// are uploaded separately, because they need their own metadata.
 fn upload_single(
     meta: &MetaRequest,
     opts: &Input,
@@ -307,7 +848,9 @@ fn upload_single(
     matcher: matching::Matcher,
 ) -> Result<BasicResult, Error> {
     log::debug!("Upload using a single request");
-    let mut files: Vec<&Path> = Vec::new();
+    let mut files: Vec<(&Path, Option<PendingItem>)> = Vec::new();
+    let mut sidecar_results = Vec::new();
+    let mut hook_failure = None;
 
     for path in &opts.files {
         if !path.exists() {
@@ -323,7 +866,18 @@ fn upload_single(
     }
 
     for path in &opts.files {
-        if matcher.is_included(path) {
+        if sidecar::is_sidecar(path) || is_error_report(path) {
+            eprintln!("Skip '{}', it belongs to another file", path.display());
+        } else if matcher.is_included(path) {
+            if !opts.dry_run {
+                if let Err(err) = hooks::run(Hook::PreUpload, path, None, None, ctx) {
+                    let message = err.to_string();
+                    eprintln!("Skip '{}': {}", path.display(), message);
+                    handle_failure(path, None, &message, opts.on_error.as_ref(), opts, ctx)?;
+                    hook_failure = Some(message);
+                    continue;
+                }
+            }
             let fauth =
                 opts.endpoint
                     .to_file_auth(ctx, &|| None)
@@ -331,22 +885,34 @@ fn upload_single(
                         path: opts.files[0].clone(),
                     })?;
 
-            let exists = check_existence(path, opts, ctx, &fauth)?;
-            if !exists {
-                eprintln!("Adding to single request: {}", path.display());
+            let dupe = check_existence(path, opts, ctx, &fauth)?;
+            let sidecar = if opts.multiple {
+                Sidecar::read(path)?
+            } else {
+                None
+            };
+            if let Some(dupe) = dupe {
+                file_exists_message(path);
+                apply_file_action(path, None, &dupe, opts, ctx)?;
+            } else if let Some(sc) = sidecar {
+                eprintln!("Uploading with sidecar file: {}", path.display());
                 if !opts.dry_run {
-                    files.push(path);
+                    sidecar_results.push(upload_with_sidecar(path, &sc, &fauth, opts, ctx)?);
                 }
             } else {
-                file_exists_message(path);
-                apply_file_action(path, None, opts)?;
+                eprintln!("Adding to single request: {}", path.display());
+                if !opts.dry_run {
+                    files.push((path, pending_item(path, None, &fauth, opts, ctx)?));
+                }
             }
         } else {
             eprintln!("Skip '{}', doesn't match given pattern(s)", path.display());
         }
     }
 
-    if !opts.dry_run {
+    let result = if !opts.dry_run {
+        let mut results: Vec<(usize, BasicResult)> =
+            sidecar_results.into_iter().map(|r| (1, r)).collect();
         if !files.is_empty() {
             let fauth =
                 opts.endpoint
@@ -355,48 +921,199 @@ fn upload_single(
                         path: opts.files[0].clone(),
                     })?;
             eprintln!("Sending request …");
+            let paths: Vec<&Path> = files.iter().map(|(path, _)| *path).collect();
             let result = ctx
                 .client
-                .upload_files(&fauth, meta, &files)
+                .upload_files(&fauth, meta, &paths)
                 .context(HttpClientSnafu)?;
             if result.success {
-                for path in &files {
-                    apply_file_action(path, None, opts)?;
+                // All files are processed together, so they share the
+                // timeout. The ids are collected before moving any file.
+                let deadline = deadline(opts);
+                let mut ids = Vec::new();
+                for (path, pending) in &files {
+                    let id = pending
+                        .as_ref()
+                        .map(|p| wait_for_item(path, p, deadline, &fauth, ctx))
+                        .transpose()
+                        .context(AfterUploadSnafu { path: *path })?;
+                    ids.push(id);
+                }
+                for (path, id) in paths.iter().zip(ids) {
+                    apply_file_action(path, None, &Outcome::Uploaded(id), opts, ctx)
+                        .context(AfterUploadSnafu { path: *path })?;
+                }
+            } else {
+                for path in &paths {
+                    let spec = opts.on_error.as_ref();
+                    handle_failure(path, None, &result.message, spec, opts, ctx)?;
                 }
             }
-            Ok(result)
-        } else {
-            Ok(BasicResult {
-                success: true,
-                message: "No files to upload".into(),
-            })
+            results.push((paths.len(), result));
         }
+        combine_results(results).unwrap_or_else(|| BasicResult {
+            success: true,
+            message: "No files to upload".into(),
+        })
     } else {
-        Ok(BasicResult {
+        BasicResult {
             success: true,
             message: format!("Would upload {} file(s)", files.len()),
+        }
+    };
+    // The other files are uploaded, but the upload as a whole failed
+    match hook_failure {
+        Some(message) if result.success => Ok(BasicResult {
+            success: false,
+            message,
+        }),
+        _ => Ok(result),
+    }
+}
+
+/// Combines the results of several upload requests, each with the
+/// number of files it contained. A single result is returned as is.
+fn combine_results(results: Vec<(usize, BasicResult)>) -> Option<BasicResult> {
+    if results.len() <= 1 {
+        return results.into_iter().next().map(|(_, r)| r);
+    }
+    let count = |success: bool| -> usize {
+        results
+            .iter()
+            .filter(|(_, r)| r.success == success)
+            .map(|(n, _)| n)
+            .sum()
+    };
+    let (uploaded, failed) = (count(true), count(false));
+    Some(BasicResult {
+        success: failed == 0,
+        message: format!("Uploaded {}, failed {}", uploaded, failed),
+    })
+}
+
+/// Uploads a single file with the metadata from its sidecar file and
+/// applies the remaining values once the item has been created.
+fn upload_with_sidecar(
+    path: &Path,
+    sidecar: &Sidecar,
+    fauth: &FileAuth,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<BasicResult, Error> {
+    let mut upload = opts.upload.clone();
+    sidecar
+        .upload_settings()
+        .apply_override(&mut upload, &mut opts.endpoint.clone());
+    let meta = upload_meta(opts.multiple, &upload);
+    let pending = pending_item(path, Some(sidecar), fauth, opts, ctx)?;
+    let result = ctx
+        .client
+        .upload_files(fauth, &meta, &[path])
+        .context(HttpClientSnafu)?;
+    if result.success {
+        after_upload(path, None, Some(sidecar), pending, fauth, opts, ctx)
+            .context(AfterUploadSnafu { path })?;
+    } else {
+        let spec = opts.on_error.as_ref();
+        handle_failure(path, None, &result.message, spec, opts, ctx)?;
+    }
+    Ok(result)
+}
+
+/// Returns whether the file is an error report written by
+/// `--on-error`. These are files named like `<name>.error.txt` next to
+/// an existing file `<name>`, they are never uploaded.
+pub fn is_error_report(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| {
+            n.to_str()?
+                .strip_suffix(&format!(".{}", ERROR_REPORT_EXT))
+                .filter(|name| !name.is_empty())
+                .map(|name| path.with_file_name(name).is_file())
         })
+        .unwrap_or(false)
+}
+
+/// Runs the error hook for a file that failed to upload and applies
+/// the error action, if given.
+pub fn handle_failure(
+    path: &Path,
+    root: Option<&PathBuf>,
+    message: &str,
+    spec: Option<&ActionSpec>,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    if !opts.dry_run {
+        hooks::notify(Hook::OnError, path, None, Some(message), ctx);
+    }
+    match spec {
+        Some(spec) => quarantine(path, root, message, spec, &opts.action),
+        None => Ok(()),
     }
 }
 
+/// Applies the error action to a file that failed to upload. If it
+/// has been moved or copied, an error report is written next to it.
+pub fn quarantine(
+    path: &Path,
+    root: Option<&PathBuf>,
+    message: &str,
+    spec: &ActionSpec,
+    action: &FileAction,
+) -> Result<(), Error> {
+    let res = action
+        .apply(spec, path, root, None)
+        .context(FileActionSnafu { path })?;
+    follow_sidecar(path, root, &res)?;
+    match res {
+        FileActionResult::Moved(p) | FileActionResult::Copied(p) => {
+            let mut name = p.file_name().unwrap_or_default().to_os_string();
+            name.push(".");
+            name.push(ERROR_REPORT_EXT);
+            let report = p.with_file_name(name);
+            let content = format!(
+                "File: {}\nTime: {}\nError: {}\n",
+                path.display(),
+                chrono::Local::now().to_rfc3339(),
+                message
+            );
+            std::fs::write(&report, content).context(ErrorReportSnafu { path: &report })?;
+            eprintln!("Put failed file to: {}", p.display());
+        }
+        FileActionResult::Deleted(_) => eprintln!("Deleted failed file"),
+        FileActionResult::Nothing => (),
+    }
+    Ok(())
+}
+
+/// Checks whether the file is already in Docspell, unless duplicates
+/// are allowed.
 fn check_existence(
     path: &Path,
     opts: &Input,
     ctx: &Context,
     fauth: &FileAuth,
-) -> Result<bool, Error> {
+) -> Result<Option<Outcome>, Error> {
     if opts.upload.skip_duplicates {
         let hash = digest::digest_file_sha256(path).context(DigestFileSnafu { path })?;
-        let exists = ctx
-            .client
-            .file_exists(hash, fauth)
-            .context(HttpClientSnafu)?;
-        Ok(exists.exists)
+        find_existing(&hash, fauth, ctx)
     } else {
-        Ok(false)
+        Ok(None)
     }
 }
 
+/// Looks up a file by its digest in Docspell.
+fn find_existing(hash: &str, fauth: &FileAuth, ctx: &Context) -> Result<Option<Outcome>, Error> {
+    let result = ctx
+        .client
+        .file_exists(hash, fauth)
+        .context(HttpClientSnafu)?;
+    Ok(result
+        .exists
+        .then(|| Outcome::Duplicate(result.items.into_iter().next().map(|i| i.id))))
+}
+
 // TODO use clap to solve this!
 fn check_flags(args: &Input) -> Result<(), Error> {
     if args.traverse && !args.multiple {
@@ -405,14 +1122,90 @@ fn check_flags(args: &Input) -> Result<(), Error> {
     if args.poll.is_some() && !args.traverse {
         return Err(Error::PollWithoutTraverse);
     }
+    if args.parallel > 1 && !args.traverse {
+        return Err(Error::ParallelWithoutTraverse);
+    }
 
     Ok(())
 }
 
 //////////////////////////////////////////////////////////////////////////////
-/// Helper types
+// Helper types
+
+/// The result of uploading a single file when traversing.
+#[derive(Debug, PartialEq, Eq)]
+enum JobResult {
+    Uploaded,
+    /// The file is already in Docspell.
+    Skipped,
+    Failed,
+}
+
+/// How a file has been handled, which decides the file action to
+/// apply. Both contain the id of the item, if it is known.
+enum Outcome {
+    Uploaded(Option<String>),
+    Duplicate(Option<String>),
+}
+
+/// The contents of the files in one traversal, by their digest. A
+/// worker holds the lock of a digest while checking and uploading the
+/// file, so that another worker with a file of the same contents waits
+/// and then skips it. The flag is set once the contents are uploaded.
+#[derive(Default)]
+struct Digests {
+    slots: Mutex<HashMap<String, Arc<Mutex<bool>>>>,
+}
+
+impl Digests {
+    fn slot(&self, digest: &str) -> Arc<Mutex<bool>> {
+        let mut slots = self.slots.lock().expect("Digest lock poisoned");
+        slots.entry(digest.to_string()).or_default().clone()
+    }
+}
+
+/// The number of failed attempts to upload a file due to transient
+/// errors. It is kept across polls, so that a file is given up after
+/// `--max-attempts`.
+#[derive(Default)]
+struct Attempts {
+    failed: Mutex<HashMap<PathBuf, u32>>,
+}
+
+impl Attempts {
+    /// Records a failed attempt and returns the number of failed
+    /// attempts so far.
+    fn failed(&self, path: &Path) -> u32 {
+        let mut failed = self.failed.lock().expect("Attempts lock poisoned");
+        let count = failed.entry(path.to_path_buf()).or_default();
+        *count += 1;
+        *count
+    }
+
+    fn clear(&self, path: &Path) {
+        self.failed
+            .lock()
+            .expect("Attempts lock poisoned")
+            .remove(path);
+    }
+}
+
+/// A file whose item id is needed after uploading it. The `known`
+/// items already contained the file before, which happens with
+/// `--allow-dupes`, so they are not the new item.
+pub struct PendingItem {
+    pub hash: String,
+    pub known: HashSet<String>,
+}
+
+/// A file to upload when traversing. The `root` is the directory
+/// given as argument that contains the file, if any.
+struct UploadJob {
+    file: PathBuf,
+    root: Option<PathBuf>,
+}
 
-mod matching {
+pub mod matching {
     use super::*;
     use glob::{GlobResult, Paths, Pattern};
 
@@ -424,11 +1217,11 @@ mod matching {
     }
 
     impl Matcher {
-        pub fn new(args: &Input) -> Result<Matcher, Error> {
-            let include = glob::Pattern::new(&args.matches).context(BadGlobPatternSnafu {
-                pattern: args.matches.clone(),
+        pub fn new(matches: &str, not_matches: &Option<String>) -> Result<Matcher, Error> {
+            let include = glob::Pattern::new(matches).context(BadGlobPatternSnafu {
+                pattern: matches.to_string(),
             })?;
-            let exclude = match &args.not_matches {
+            let exclude = match not_matches {
                 Some(nm) => Some(glob::Pattern::new(nm).context(BadGlobPatternSnafu {
                     pattern: nm.to_string(),
                 })?),
@@ -437,9 +1230,9 @@ mod matching {
 
             Ok(Matcher {
                 include,
-                include_glob: args.matches.clone(),
+                include_glob: matches.to_string(),
                 exclude,
-                exclude_glob: args.not_matches.clone(),
+                exclude_glob: not_matches.clone(),
             })
         }
 
@@ -0,0 +1,227 @@
+//! Upload metadata defined per directory.
+//!
+//! A directory may contain a file `.dsc.toml` that specifies metadata
+//! for all files below it, for example:
+//!
+//! ```toml
+//! tags = ["invoice"]
+//! folder = "Finance"
+//! direction = "in"
+//! language = "deu"
+//! source = "<source-id>"
+//! ```
+//!
+//! The files are looked up from the directory given as argument down
+//! to the directory containing the file to upload. Settings of deeper
+//! directories replace those of their parents, tags are added up.
+//! Options given on the command line take precedence over the
+//! result, except for tags which are added to the ones from the
+//! command line.
+
+use serde::Deserialize;
+use snafu::ResultExt;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::{DirConfigParseSnafu, DirConfigReadSnafu, Error};
+use crate::cli::opts::{Direction, EndpointOpts, UploadMeta};
+
+/// The name of the file containing the directory settings.
+pub const DIR_CONFIG_FILE: &str = ".dsc.toml";
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirConfig {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub folder: Option<String>,
+    pub direction: Option<Direction>,
+    pub language: Option<String>,
+    /// A source id to upload to. It is not used with the integration
+    /// endpoint.
+    pub source: Option<String>,
+}
+
+/// The config files read so far, by directory. A file is only read
+/// again if its modification time or size changed.
+#[derive(Debug, Default)]
+pub struct DirConfigs {
+    dirs: Mutex<HashMap<PathBuf, (Stamp, Option<DirConfig>)>>,
+}
+
+/// The modification time and size of a config file, if it exists.
+type Stamp = Option<(SystemTime, u64)>;
+
+impl DirConfigs {
+    /// Reads and merges all config files from `root` down to the
+    /// directory of `file`. Without a root, only the directory of the
+    /// file is looked at.
+    pub fn resolve(&self, file: &Path, root: Option<&Path>) -> Result<DirConfig, Error> {
+        let mut result = DirConfig::default();
+        let dir = match file.parent() {
+            Some(d) => d,
+            None => return Ok(result),
+        };
+        let root = root.filter(|r| dir.starts_with(r)).unwrap_or(dir);
+        let mut dirs: Vec<&Path> = dir
+            .ancestors()
+            .take_while(|d| d.starts_with(root))
+            .collect();
+        dirs.reverse();
+        for dir in dirs {
+            if let Some(cfg) = self.get(dir)? {
+                result.merge(cfg);
+            }
+        }
+        Ok(result)
+    }
+
+    fn get(&self, dir: &Path) -> Result<Option<DirConfig>, Error> {
+        let path = dir.join(DIR_CONFIG_FILE);
+        let stamp = std::fs::metadata(&path)
+            .and_then(|m| Ok((m.modified()?, m.len())))
+            .ok();
+        let mut dirs = self.dirs.lock().expect("Directory config cache poisoned");
+        if let Some((seen, cfg)) = dirs.get(dir) {
+            if *seen == stamp {
+                return Ok(cfg.clone());
+            }
+        }
+        let cfg = if path.is_file() {
+            log::debug!("Reading directory config {}", path.display());
+            let cnt = std::fs::read_to_string(&path)
+                .context(DirConfigReadSnafu { path: path.clone() })?;
+            Some(toml::from_str(&cnt).context(DirConfigParseSnafu { path })?)
+        } else {
+            None
+        };
+        dirs.insert(dir.to_path_buf(), (stamp, cfg.clone()));
+        Ok(cfg)
+    }
+}
+
+impl DirConfig {
+    /// Applies these settings to the options from the command line.
+    /// Options given there are kept, tags are added.
+    pub fn apply(self, meta: &mut UploadMeta, endpoint: &mut EndpointOpts) {
+        let mut tags = self.tags;
+        merge_tags(&mut meta.tag, &mut tags);
+        meta.folder = meta.folder.take().or(self.folder);
+        meta.direction = meta.direction.take().or(self.direction);
+        meta.language = meta.language.take().or(self.language);
+        if !endpoint.integration && endpoint.source.is_none() {
+            endpoint.source = self.source;
+        }
+    }
+
+    /// Applies these settings to the options, replacing the values
+    /// given there. Tags are added.
+    pub fn apply_override(self, meta: &mut UploadMeta, endpoint: &mut EndpointOpts) {
+        let mut tags = self.tags;
+        merge_tags(&mut meta.tag, &mut tags);
+        meta.folder = self.folder.or(meta.folder.take());
+        meta.direction = self.direction.or(meta.direction.take());
+        meta.language = self.language.or(meta.language.take());
+        if !endpoint.integration && self.source.is_some() {
+            endpoint.source = self.source;
+        }
+    }
+
+    fn merge(&mut self, mut other: DirConfig) {
+        merge_tags(&mut self.tags, &mut other.tags);
+        self.folder = other.folder.or(self.folder.take());
+        self.direction = other.direction.or(self.direction.take());
+        self.language = other.language.or(self.language.take());
+        self.source = other.source.or(self.source.take());
+    }
+}
+
+/// Returns whether the given file is a directory config file, which
+/// should not be uploaded.
+pub fn is_config_file(path: &Path) -> bool {
+    path.file_name() == Some(DIR_CONFIG_FILE.as_ref())
+}
+
+fn merge_tags(tags: &mut Vec<String>, more: &mut Vec<String>) {
+    more.retain(|t| !tags.contains(t));
+    tags.append(more);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_dir_config_resolve() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let dir = root.join("invoices").join("2024");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            root.join(DIR_CONFIG_FILE),
+            "tags = [\"scan\"]\nfolder = \"Inbox\"\nlanguage = \"deu\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("invoices").join(DIR_CONFIG_FILE),
+            "tags = [\"invoice\", \"scan\"]\nfolder = \"Finance\"\ndirection = \"in\"\n",
+        )
+        .unwrap();
+
+        let configs = DirConfigs::default();
+        let cfg = configs.resolve(&dir.join("a.pdf"), Some(root)).unwrap();
+        assert_eq!(cfg.tags, vec!["scan", "invoice"]);
+        assert_eq!(cfg.folder.as_deref(), Some("Finance"));
+        assert_eq!(cfg.language.as_deref(), Some("deu"));
+        assert!(matches!(cfg.direction, Some(Direction::In)));
+
+        let cfg = configs.resolve(&dir.join("a.pdf"), None).unwrap();
+        assert!(cfg.tags.is_empty() && cfg.folder.is_none());
+
+        std::fs::write(root.join(DIR_CONFIG_FILE), "folders = 1").unwrap();
+        assert!(configs.resolve(&dir.join("a.pdf"), Some(root)).is_err());
+        assert!(is_config_file(&root.join(DIR_CONFIG_FILE)));
+    }
+
+    #[test]
+    fn unit_dir_config_apply() {
+        let cfg = DirConfig {
+            tags: vec!["invoice".into()],
+            folder: Some("Finance".into()),
+            direction: Some(Direction::In),
+            language: Some("deu".into()),
+            source: Some("src2".into()),
+        };
+        let mut meta = UploadMeta {
+            direction: None,
+            folder: Some("Inbox".into()),
+            skip_duplicates: true,
+            tag: vec!["scan".into()],
+            file_filter: None,
+            language: None,
+            attachments_only: false,
+            flatten_archives: false,
+        };
+        let mut endpoint = EndpointOpts {
+            basic_file: None,
+            header_file: None,
+            basic: None,
+            header: None,
+            integration: false,
+            collective: None,
+            source: Some("src1".into()),
+        };
+        cfg.clone().apply(&mut meta, &mut endpoint);
+        assert_eq!(meta.tag, vec!["scan", "invoice"]);
+        assert_eq!(meta.folder.as_deref(), Some("Inbox"));
+        assert_eq!(meta.language.as_deref(), Some("deu"));
+        assert!(matches!(meta.direction, Some(Direction::In)));
+        assert_eq!(endpoint.source.as_deref(), Some("src1"));
+
+        cfg.apply_override(&mut meta, &mut endpoint);
+        assert_eq!(meta.folder.as_deref(), Some("Finance"));
+        assert_eq!(endpoint.source.as_deref(), Some("src2"));
+    }
+}
@@ -0,0 +1,193 @@
+//! User commands run while uploading files.
+//!
+//! Hooks are configured in the config file like the `pdf_viewer`: a
+//! list where the first element is the program and the others are
+//! its arguments. Any `{}` in an argument is replaced by the path of
+//! the file.
+//!
+//! ```toml
+//! pre_upload_hook = ["ocrmypdf", "--skip-text", "{}", "{}"]
+//! post_upload_hook = ["notify-send", "Uploaded {}"]
+//! ```
+//!
+//! The hook gets the environment variables `DSC_HOOK`, `DSC_FILE`,
+//! `DSC_ITEM_ID` (if known) and `DSC_MESSAGE` (if any). The same
+//! values are written as JSON to its standard input:
+//!
+//! ```json
+//! {"hook":"post-upload","file":"/tmp/a.pdf","itemId":null,"message":null}
+//! ```
+//!
+//! The output of the hook is printed to stderr. If the pre-upload
+//! hook fails, the file is not uploaded and counts as failed.
+
+use serde::Serialize;
+use snafu::ResultExt;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use super::{Error, HookRunSnafu};
+use crate::cli::cmd::Context;
+use crate::config::DsConfig;
+
+/// The points during an upload where a hook can be run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Before a file is checked and uploaded. If it fails, the file
+    /// is not uploaded.
+    PreUpload,
+    /// After a file has been uploaded, before the file action.
+    PostUpload,
+    /// When a file is already in Docspell, before the file action.
+    OnDuplicate,
+    /// When a file failed to upload, before the error action.
+    OnError,
+}
+
+impl Hook {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::PreUpload => "pre-upload",
+            Hook::PostUpload => "post-upload",
+            Hook::OnDuplicate => "on-duplicate",
+            Hook::OnError => "on-error",
+        }
+    }
+
+    /// Returns the configured command, if any.
+    fn command<'a>(&self, cfg: &'a DsConfig) -> Option<&'a [String]> {
+        match self {
+            Hook::PreUpload => cfg.pre_upload_hook.as_deref(),
+            Hook::PostUpload => cfg.post_upload_hook.as_deref(),
+            Hook::OnDuplicate => cfg.on_duplicate_hook.as_deref(),
+            Hook::OnError => cfg.on_error_hook.as_deref(),
+        }
+        .filter(|cmd| !cmd.is_empty())
+    }
+}
+
+/// The data passed to a hook.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookEvent<'a> {
+    pub hook: &'static str,
+    pub file: &'a Path,
+    pub item_id: Option<&'a str>,
+    pub message: Option<&'a str>,
+}
+
+/// Runs the hook, if it is configured. Returns an error if it can't
+/// be started or exits with a failure.
+pub fn run(
+    hook: Hook,
+    file: &Path,
+    item_id: Option<&str>,
+    message: Option<&str>,
+    ctx: &Context,
+) -> Result<(), Error> {
+    match hook.command(ctx.cfg) {
+        Some(cmd) => {
+            let event = HookEvent {
+                hook: hook.name(),
+                file,
+                item_id,
+                message,
+            };
+            exec(cmd, &event)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Runs a hook that only reports about a file. If it fails, a warning
+/// is printed, but the upload continues.
+pub fn notify(
+    hook: Hook,
+    file: &Path,
+    item_id: Option<&str>,
+    message: Option<&str>,
+    ctx: &Context,
+) {
+    if let Err(err) = run(hook, file, item_id, message, ctx) {
+        eprintln!("Warning: {}", err);
+    }
+}
+
+fn exec(cmd: &[String], event: &HookEvent) -> Result<(), Error> {
+    let file = event.file.display().to_string();
+    let (tool, tool_args) = cmd.split_first().expect("hook command is not empty");
+    let args: Vec<String> = tool_args.iter().map(|s| s.replace("{}", &file)).collect();
+    log::info!("Run {} hook: {} {}", event.hook, tool, args.join(" "));
+
+    let mut command = Command::new(tool);
+    command
+        .args(&args)
+        .env("DSC_HOOK", event.hook)
+        .env("DSC_FILE", &file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::from(std::io::stderr()));
+    if let Some(id) = event.item_id {
+        command.env("DSC_ITEM_ID", id);
+    }
+    if let Some(msg) = event.message {
+        command.env("DSC_MESSAGE", msg);
+    }
+    let mut child = command.spawn().context(HookRunSnafu { hook: event.hook })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // the hook may not read its input, so a broken pipe is fine
+        let json = serde_json::to_vec(event).unwrap_or_default();
+        if let Err(err) = stdin.write_all(&json) {
+            log::debug!("Could not write to {} hook: {}", event.hook, err);
+        }
+    }
+    let status = child.wait().context(HookRunSnafu { hook: event.hook })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::HookFailed {
+            hook: event.hook.to_string(),
+            path: event.file.to_path_buf(),
+            status: status.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_hook_exec() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let file = dir.join("a.pdf");
+        let out = dir.join("out.txt");
+        let cmd: Vec<String> = vec![
+            "sh".into(),
+            "-c".into(),
+            "echo \"$DSC_HOOK $DSC_ITEM_ID $1\" > \"$0\"; cat >> \"$0\"".into(),
+            out.display().to_string(),
+            "{}".into(),
+        ];
+        let event = HookEvent {
+            hook: Hook::PostUpload.name(),
+            file: &file,
+            item_id: Some("item1"),
+            message: None,
+        };
+        exec(&cmd, &event).unwrap();
+        let content = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "post-upload item1 {}\n{}",
+                file.display(),
+                serde_json::to_string(&event).unwrap()
+            )
+        );
+
+        let fail: Vec<String> = vec!["sh".into(), "-c".into(), "exit 2".into()];
+        assert!(matches!(exec(&fail, &event), Err(Error::HookFailed { .. })));
+    }
+}
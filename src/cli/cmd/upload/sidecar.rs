@@ -0,0 +1,223 @@
+//! Metadata files next to uploaded files.
+//!
+//! A file `scan001.pdf.json` next to `scan001.pdf` can specify
+//! metadata for the item created from it:
+//!
+//! ```json
+//! {
+//!   "tags": ["invoice"],
+//!   "folder": "Finance",
+//!   "direction": "in",
+//!   "language": "deu",
+//!   "name": "Electricity bill",
+//!   "date": "2024-01-31",
+//!   "dueDate": "2024-02-15",
+//!   "correspondent": "ACME",
+//!   "notes": "Paid by bank transfer",
+//!   "fields": { "amount": "12.50" }
+//! }
+//! ```
+//!
+//! Tags, folder, direction and language are sent with the upload and
+//! take precedence over all other settings. The remaining values are
+//! set on the item after the server has processed it, which requires
+//! to be logged in. The correspondent is looked up as organization
+//! first, then as person.
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use snafu::ResultExt;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::dirconfig::DirConfig;
+use super::{check, Error, HttpClientSnafu, SidecarParseSnafu, SidecarReadSnafu};
+use crate::cli::cmd::Context;
+use crate::cli::opts::Direction;
+use crate::http::payload::{CustomFieldValue, OptionalDate, OptionalId, OptionalText};
+
+/// The extension that is appended to a file name to get the name of
+/// its sidecar file.
+pub const SIDECAR_EXT: &str = "json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Sidecar {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub folder: Option<String>,
+    pub direction: Option<Direction>,
+    pub language: Option<String>,
+    pub name: Option<String>,
+    /// A date like `2024-01-31`.
+    pub date: Option<String>,
+    pub due_date: Option<String>,
+    pub correspondent: Option<String>,
+    pub notes: Option<String>,
+    /// Custom field values by field name.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Sidecar {
+    /// Returns the path of the sidecar file for the given file.
+    pub fn path_for(file: &Path) -> PathBuf {
+        let mut name = file.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(SIDECAR_EXT);
+        file.with_file_name(name)
+    }
+
+    /// Reads the sidecar file of the given file, if it exists.
+    pub fn read(file: &Path) -> Result<Option<Sidecar>, Error> {
+        let path = Self::path_for(file);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        log::debug!("Reading sidecar file {}", path.display());
+        let cnt = std::fs::read(&path).context(SidecarReadSnafu { path: &path })?;
+        let sidecar: Sidecar =
+            serde_json::from_slice(&cnt).context(SidecarParseSnafu { path: &path })?;
+        for date in [&sidecar.date, &sidecar.due_date].into_iter().flatten() {
+            parse_date(date).map_err(|message| Error::SidecarInvalid {
+                path: path.clone(),
+                message,
+            })?;
+        }
+        Ok(Some(sidecar))
+    }
+
+    /// Returns the settings that are sent with the upload.
+    pub fn upload_settings(&self) -> DirConfig {
+        DirConfig {
+            tags: self.tags.clone(),
+            folder: self.folder.clone(),
+            direction: self.direction.clone(),
+            language: self.language.clone(),
+            source: None,
+        }
+    }
+
+    /// Returns whether there are values to set after the item has been
+    /// created.
+    pub fn has_updates(&self) -> bool {
+        self.name.is_some()
+            || self.date.is_some()
+            || self.due_date.is_some()
+            || self.correspondent.is_some()
+            || self.notes.is_some()
+            || !self.fields.is_empty()
+    }
+
+    /// Sets the remaining values on the item created for the uploaded
+    /// file.
+    pub fn apply(&self, id: &str, ctx: &Context) -> Result<(), Error> {
+        log::debug!("Applying sidecar values to item {}", id);
+        let token = &ctx.opts.session;
+        let client = &ctx.client;
+        if let Some(name) = &self.name {
+            let text = OptionalText {
+                text: Some(name.clone()),
+            };
+            check(client.set_item_name(token, id, &text))?;
+        }
+        if let Some(date) = &self.date {
+            let date = OptionalDate {
+                date: parse_date(date).ok(),
+            };
+            check(client.set_item_date(token, id, &date))?;
+        }
+        if let Some(date) = &self.due_date {
+            let date = OptionalDate {
+                date: parse_date(date).ok(),
+            };
+            check(client.set_due_date(token, id, &date))?;
+        }
+        if let Some(notes) = &self.notes {
+            let text = OptionalText {
+                text: Some(notes.clone()),
+            };
+            check(client.set_item_notes(token, id, &text))?;
+        }
+        if let Some(name) = &self.correspondent {
+            let org = client
+                .find_organization(token, name)
+                .context(HttpClientSnafu)?;
+            if let Some(org) = org {
+                check(client.set_corr_org(token, id, &OptionalId { id: Some(org.id) }))?;
+            } else if let Some(person) = client.find_person(token, name).context(HttpClientSnafu)? {
+                let person = OptionalId {
+                    id: Some(person.id),
+                };
+                check(client.set_corr_person(token, id, &person))?;
+            } else {
+                eprintln!("Warning: correspondent '{}' not found, skipping it", name);
+            }
+        }
+        for (field, value) in &self.fields {
+            let value = CustomFieldValue {
+                field: field.clone(),
+                value: value.clone(),
+            };
+            check(client.set_field(token, id, &value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the file is a sidecar file. These are files named
+/// like `<name>.<ext>.json` next to an existing file `<name>.<ext>`,
+/// they are never uploaded themselves.
+pub fn is_sidecar(path: &Path) -> bool {
+    path.extension().map(|e| e == SIDECAR_EXT).unwrap_or(false)
+        && path
+            .file_stem()
+            .filter(|stem| Path::new(stem).extension().is_some())
+            .map(|stem| path.with_file_name(stem).is_file())
+            .unwrap_or(false)
+}
+
+/// Parses a date like `2024-01-31` into milliseconds since the epoch.
+fn parse_date(s: &str) -> Result<i64, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis())
+        .map_err(|e| format!("Expected a date like 2021-12-31, got '{}': {}", s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_sidecar_read() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let file = dir.join("scan001.pdf");
+        std::fs::write(&file, "pdf").unwrap();
+        assert!(Sidecar::read(&file).unwrap().is_none());
+
+        let sidecar = Sidecar::path_for(&file);
+        assert_eq!(sidecar, dir.join("scan001.pdf.json"));
+        assert!(is_sidecar(&sidecar));
+        assert!(!is_sidecar(&dir.join("data.json")));
+        // without the companion file, it is uploaded like any other
+        assert!(!is_sidecar(&dir.join("export.tar.json")));
+
+        std::fs::write(
+            &sidecar,
+            r#"{"tags": ["invoice"], "folder": "Finance", "name": "Bill",
+                "date": "2024-01-31", "fields": {"amount": "12.50"}}"#,
+        )
+        .unwrap();
+        let cfg = Sidecar::read(&file).unwrap().unwrap();
+        assert!(cfg.has_updates());
+        assert_eq!(cfg.fields.get("amount").map(String::as_str), Some("12.50"));
+        let settings = cfg.upload_settings();
+        assert_eq!(settings.tags, vec!["invoice"]);
+        assert_eq!(settings.folder.as_deref(), Some("Finance"));
+        assert_eq!(parse_date("2024-01-31"), Ok(1706659200000));
+
+        std::fs::write(&sidecar, r#"{"date": "31.01.2024"}"#).unwrap();
+        assert!(Sidecar::read(&file).is_err());
+    }
+}
@@ -99,7 +99,7 @@ pub fn view_all(opts: &Input, ctx: &Context, parent: &Path) -> Result<(), Error>
 
         let file = download(&dref, ctx, parent)?;
         if let Some(f) = file {
-            let tool = &ctx.cfg.pdf_viewer.get(0).ok_or(Error::NoPdfViewer)?;
+            let tool = &ctx.cfg.pdf_viewer.first().ok_or(Error::NoPdfViewer)?;
             let tool_args: Vec<String> = ctx
                 .cfg
                 .pdf_viewer
@@ -1,13 +1,26 @@
+mod index;
+mod queue;
+
 use clap::{Parser, ValueHint};
-use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use notify_debouncer_full::notify::event::{EventKind, ModifyKind, RenameMode};
+use notify_debouncer_full::notify::{self, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebouncedEvent};
+use sha2::{Digest, Sha256};
 use snafu::{ResultExt, Snafu};
-use std::{path::Path, sync::mpsc};
-use std::{path::PathBuf, time::Duration};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use std::{path::Path, path::PathBuf, sync::mpsc};
 
+use self::index::PollIndex;
+use self::queue::UploadQueue;
+use super::upload::dirconfig::DirConfigs;
+use super::upload::hooks::Hook;
+use super::upload::ErrorKind;
 use super::{upload, Cmd, Context};
 use crate::http::payload::BasicResult;
+use crate::http::Client;
 use crate::{
-    cli::opts::{EndpointOpts, FileAction, UploadMeta},
+    cli::opts::{ActionSpec, EndpointOpts, FileAction, UploadMeta},
     util::file::CollectiveSubdirErr,
 };
 
@@ -22,9 +35,30 @@ use crate::util::file;
 /// specified, it will be guessed from the first subdirectory of the
 /// directory that is specified.
 ///
-/// On some filesystems, this command may not work (e.g. networking
-/// file systems like NFS or SAMBA). You may use the `upload` command
-/// then in combination with the `--poll` option.
+/// Files that are created, written or moved into a watched directory
+/// are uploaded. If a directory is moved into a directory that is
+/// watched recursively, all files in it are uploaded.
+///
+/// A file `.dsc.toml` in a watched directory or its subdirectories
+/// can specify tags, folder, direction, language and source for the
+/// files below it, see `upload`. Sidecar files like
+/// `scan001.pdf.json` are supported as well. They should be written
+/// before the file they belong to. The upload hooks from the config
+/// file are run as well. Changes a pre-upload hook makes to a file
+/// don't cause it to be uploaded again.
+///
+/// Detected files are put into a queue that is stored in a file. If
+/// an upload fails, because the server is not reachable, doesn't
+/// answer in time or responds with a server error, it is retried
+/// later with an increasing delay. Files still in the queue are
+/// uploaded when the watcher is started again. Errors that would
+/// happen for every file, like invalid credentials, stop the watcher
+/// and leave the file in the queue.
+///
+/// On some filesystems, watching may not work (e.g. networking file
+/// systems like NFS or SAMBA). Use the `--poll` option then, to
+/// traverse the directories periodically instead. Only files that are
+/// new or have changed since the last traversal are considered.
 #[derive(Parser, Debug)]
 pub struct Input {
     /// Wether to watch directories recursively or not.
@@ -55,6 +89,64 @@ pub struct Input {
     #[arg(long)]
     pub dry_run: bool,
 
+    /// Wait until a file didn't change its size and modification
+    /// time for this many seconds, before uploading it. This prevents
+    /// uploading files that are still being written. Use 0 to upload
+    /// right away.
+    #[arg(long, default_value = "3")]
+    pub stable_secs: u64,
+
+    /// Ignore files whose name matches this glob pattern. These are
+    /// usually temporary files created while writing a file. Can be
+    /// given multiple times, replacing the defaults.
+    #[arg(long = "ignore", value_name = "GLOB", default_values_t = default_ignore())]
+    pub ignore: Vec<glob::Pattern>,
+
+    /// Don't watch for file system events, but traverse the
+    /// directories every given number of seconds. An index of the
+    /// files' size and modification time is kept, so that only new or
+    /// changed files are checked.
+    #[arg(long, value_name = "SECS")]
+    pub poll: Option<u64>,
+
+    /// The file to store the index of files seen when polling.
+    /// Defaults to `watch-index-<key>.json` in the config directory,
+    /// like the queue file.
+    #[arg(long, value_hint = ValueHint::FilePath, requires = "poll")]
+    pub index_file: Option<PathBuf>,
+
+    /// Upload files that are already in the directories when
+    /// starting. Files that are already in Docspell are skipped and
+    /// left in place, unless `--allow-dupes` is given. This is always
+    /// done with `--poll`.
+    #[arg(long)]
+    pub scan_existing: bool,
+
+    /// The action for files that can't be uploaded, because the
+    /// server rejected them, their sidecar file is invalid or the
+    /// pre-upload hook failed: `keep`, `delete`, `move:<dir>` or
+    /// `copy:<dir>`. When moved or copied, an error report
+    /// `<file>.error.txt` is written next to the file. Files that fail
+    /// because the server can't be reached, doesn't answer in time or
+    /// responds with a server error are retried instead, until
+    /// `--max-attempts` is reached. Without this, the watcher stops
+    /// at such an error or retries the file forever.
+    #[arg(long, value_name = "ACTION")]
+    pub on_error: Option<ActionSpec>,
+
+    /// How often a file is attempted, if it fails because the server
+    /// can't be reached, doesn't answer in time or responds with a
+    /// server error, before the `--on-error` action is applied to it.
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_attempts: u32,
+
+    /// The file to store the queue of files to upload. Defaults to
+    /// `watch-queue-<key>.json` in the config directory, where the
+    /// key is derived from the watched directories and the upload
+    /// endpoint.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub queue_file: Option<PathBuf>,
+
     #[clap(flatten)]
     pub endpoint: EndpointOpts,
 
@@ -63,6 +155,21 @@ pub struct Input {
     pub dirs: Vec<PathBuf>,
 }
 
+fn default_ignore() -> Vec<glob::Pattern> {
+    [
+        "*.tmp",
+        "*.part",
+        "*.crdownload",
+        "*.lock",
+        "~$*",
+        ".~lock.*#",
+        ".*.swp",
+    ]
+    .iter()
+    .map(|s| glob::Pattern::new(s).expect("valid default pattern"))
+    .collect()
+}
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("Uploading failed: {}", source))]
@@ -81,7 +188,13 @@ pub enum Error {
     Watch { source: notify::Error },
 
     #[snafu(display("Error consuming event: {}", source))]
-    Event { source: mpsc::RecvError },
+    Event { source: mpsc::RecvTimeoutError },
+
+    #[snafu(display("Error accessing the upload queue: {}", source))]
+    Queue { source: std::io::Error },
+
+    #[snafu(display("Error accessing the poll index: {}", source))]
+    Index { source: std::io::Error },
 
     #[snafu(display("Error finding collective: {}", source))]
     FindCollective { source: CollectiveSubdirErr },
@@ -90,6 +203,19 @@ pub enum Error {
     NoCollective { path: PathBuf },
 }
 
+impl Error {
+    /// How the error affects the upload of a file, see
+    /// [`upload::Error::kind`]. A file without a collective fails on
+    /// its own.
+    fn kind(&self, client: &Client) -> ErrorKind {
+        match self {
+            Error::Upload { source } => source.kind(client),
+            Error::FindCollective { .. } | Error::NoCollective { .. } => ErrorKind::File,
+            _ => ErrorKind::Fatal,
+        }
+    }
+}
+
 impl Cmd for Input {
     type CmdError = Error;
 
@@ -101,6 +227,23 @@ impl Cmd for Input {
 
 pub fn watch_directories(opts: &Input, ctx: &Context) -> Result<(), Error> {
     check_is_dir(&opts.dirs)?;
+    let mut queue = UploadQueue::load(queue_file(opts, ctx)).context(QueueSnafu)?;
+    if !queue.is_empty() {
+        eprintln!("Uploading {} files from the queue", queue.len());
+    }
+    let configs = DirConfigs::default();
+    match opts.poll {
+        Some(secs) => poll_directories(Duration::from_secs(secs), &mut queue, &configs, opts, ctx),
+        None => notify_directories(&mut queue, &configs, opts, ctx),
+    }
+}
+
+fn notify_directories(
+    queue: &mut UploadQueue,
+    configs: &DirConfigs,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
     let mode = if opts.recursive {
         RecursiveMode::Recursive
     } else {
@@ -108,19 +251,335 @@ pub fn watch_directories(opts: &Input, ctx: &Context) -> Result<(), Error> {
     };
     let (tx, rx) = mpsc::channel();
 
-    let mut watcher =
-        notify::watcher(tx, Duration::from_secs(opts.delay_secs)).context(WatchSnafu)?;
+    let mut debouncer =
+        new_debouncer(Duration::from_secs(opts.delay_secs), None, tx).context(WatchSnafu)?;
     for dir in &opts.dirs {
         eprintln!("Watching directory ({:?}): {}", mode, dir.display());
-        watcher.watch(dir, mode).context(WatchSnafu)?;
+        debouncer.watch(dir, mode).context(WatchSnafu)?;
     }
+    if opts.scan_existing {
+        scan(queue, None, configs, opts, ctx)?;
+    }
+    process_queue(queue, configs, opts, ctx)?;
     eprintln!("Press Ctrl-C to quit.");
     loop {
-        match rx.recv() {
-            Ok(event) => event_act(event, opts, ctx)?,
+        let timeout = queue.next_due().unwrap_or(IDLE_TIMEOUT);
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(events)) => {
+                for event in &events {
+                    event_act(event, queue, opts)?;
+                }
+                queue.save().context(QueueSnafu)?;
+            }
+            Ok(Err(errors)) => {
+                for err in errors {
+                    log::error!("Error while watching: {}", err);
+                    if is_fatal(&err, opts) {
+                        return Err(Error::Watch { source: err });
+                    }
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => (),
             Err(e) => return Err(Error::Event { source: e }),
         }
+        process_queue(queue, configs, opts, ctx)?;
+    }
+}
+
+/// Whether the watcher can't continue after this error. Other errors,
+/// like a file that is removed while it is looked at, only affect
+/// single events.
+fn is_fatal(err: &notify::Error, opts: &Input) -> bool {
+    matches!(
+        err.kind,
+        notify::ErrorKind::MaxFilesWatch | notify::ErrorKind::InvalidConfig(_)
+    ) || opts.dirs.iter().any(|dir| !dir.is_dir())
+}
+
+/// Traverses the directories periodically and adds new or changed
+/// files to the queue.
+fn poll_directories(
+    interval: Duration,
+    queue: &mut UploadQueue,
+    configs: &DirConfigs,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    let index_file = if opts.dry_run {
+        None
+    } else {
+        let key = watcher_key(opts, ctx);
+        opts.index_file
+            .clone()
+            .or_else(|| PollIndex::default_file(&key))
+    };
+    let mut index = PollIndex::load(index_file).context(IndexSnafu)?;
+    for dir in &opts.dirs {
+        eprintln!(
+            "Polling directory (every {:?}): {}",
+            interval,
+            dir.display()
+        );
+    }
+    eprintln!("Press Ctrl-C to quit.");
+    let mut next_poll = Instant::now();
+    loop {
+        if Instant::now() >= next_poll {
+            scan(queue, Some(&mut index), configs, opts, ctx)?;
+            next_poll = Instant::now() + interval;
+        }
+        process_queue(queue, configs, opts, ctx)?;
+        let until_poll = next_poll.saturating_duration_since(Instant::now());
+        std::thread::sleep(queue.next_due().unwrap_or(until_poll).min(until_poll));
+    }
+}
+
+/// How long to wait for events if the queue is empty.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The queue is only kept in memory for a dry run.
+fn queue_file(opts: &Input, ctx: &Context) -> Option<PathBuf> {
+    if opts.dry_run {
+        None
+    } else {
+        let key = watcher_key(opts, ctx);
+        opts.queue_file
+            .clone()
+            .or_else(|| UploadQueue::default_file(&key))
+    }
+}
+
+/// Identifies a watcher by its directories and upload endpoint. It
+/// names the default files of the watcher, so that watchers for
+/// different directories or servers don't share them.
+fn watcher_key(opts: &Input, ctx: &Context) -> String {
+    let mut dirs: Vec<PathBuf> = opts
+        .dirs
+        .iter()
+        .map(|d| d.canonicalize().unwrap_or_else(|_| d.clone()))
+        .collect();
+    dirs.sort();
+    let endpoint = &opts.endpoint;
+    let mut key = format!(
+        "{}|{}|{:?}|{:?}",
+        ctx.base_url(),
+        endpoint.integration,
+        endpoint.get_source_id(ctx.cfg),
+        endpoint.collective
+    );
+    for dir in dirs {
+        key.push('|');
+        key.push_str(&dir.to_string_lossy());
+    }
+    hex::encode(Sha256::digest(key.as_bytes()))[..16].to_string()
+}
+
+/// Uploads all files in the queue that are due. Files that fail to
+/// upload, because the server can't be reached, doesn't answer in
+/// time or responds with a server error, stay in the queue to be
+/// retried later, until `--max-attempts` is reached. Files that
+/// can't be uploaded, like a rejected file, are removed from the
+/// queue and handled by `--on-error`. Other errors stop the watcher.
+/// The queue is saved afterwards.
+fn process_queue(
+    queue: &mut UploadQueue,
+    configs: &DirConfigs,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    let result = process_due(queue, configs, opts, ctx);
+    queue.save().context(QueueSnafu)?;
+    result
+}
+
+fn process_due(
+    queue: &mut UploadQueue,
+    configs: &DirConfigs,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    for path in queue.due() {
+        if !path.is_file() {
+            log::info!("Removing {} from queue, it doesn't exist", path.display());
+            queue.remove(&path);
+            continue;
+        }
+        if !is_stable(&path, queue, opts) {
+            continue;
+        }
+        match upload_and_report(path.clone(), configs, opts, ctx) {
+            Ok(()) => queue.processed(&path, observe(&path).ok()),
+            Err(err) => {
+                let kind = err.kind(&ctx.client);
+                if kind == ErrorKind::Transient {
+                    let delay = queue.failed(&path, err.to_string());
+                    if opts.on_error.is_none() || queue.attempts(&path) < opts.max_attempts {
+                        log::error!("Uploading {} failed: {}", path.display(), err);
+                        eprintln!("Upload failed, retrying in {}s: {}", delay.as_secs(), err);
+                        continue;
+                    }
+                }
+                let msg = match kind {
+                    ErrorKind::Transient => {
+                        format!("Giving up after {} attempts: {}", opts.max_attempts, err)
+                    }
+                    _ => err.to_string(),
+                };
+                if !opts.dry_run {
+                    upload::hooks::notify(Hook::OnError, &path, None, Some(&msg), ctx);
+                }
+                // The file stays in the queue, so it is uploaded when
+                // the watcher is started again after fixing the problem.
+                if kind == ErrorKind::Fatal {
+                    return Err(err);
+                }
+                queue.processed(&path, observe(&path).ok());
+                // A file that has been uploaded is not put aside
+                let uploaded = matches!(
+                    err,
+                    Error::Upload {
+                        source: upload::Error::AfterUpload { .. }
+                    }
+                );
+                match &opts.on_error {
+                    _ if uploaded => {
+                        log::error!("{}", err);
+                        eprintln!("Error: {}", err);
+                    }
+                    Some(spec) => {
+                        log::error!("Uploading {} failed: {}", path.display(), err);
+                        eprintln!("Upload failed: {}", err);
+                        let root = watched_dir(&path, opts);
+                        upload::quarantine(&path, root, &msg, spec, &opts.action)
+                            .context(UploadSnafu)?;
+                    }
+                    None => return Err(err),
+                }
+            }
+        }
     }
+    Ok(())
+}
+
+/// Adds all files in the watched directories to the queue that match
+/// the given patterns and are not yet in Docspell.
+fn scan(
+    queue: &mut UploadQueue,
+    mut index: Option<&mut PollIndex>,
+    configs: &DirConfigs,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    let matcher =
+        upload::matching::Matcher::new(&opts.matches, &opts.not_matches).context(UploadSnafu)?;
+    let mut seen = HashSet::new();
+    for dir in &opts.dirs {
+        log::info!("Scanning files in {}", dir.display());
+        for file in matcher.traverse(dir).context(UploadSnafu)? {
+            if !opts.recursive && file.parent() != Some(dir.as_path()) || is_ignored(&file, opts) {
+                continue;
+            }
+            if let Some(index) = index.as_deref_mut() {
+                let observed = match observe(&file) {
+                    Ok(o) => o,
+                    Err(err) => {
+                        log::debug!("Cannot read metadata of {}: {}", file.display(), err);
+                        continue;
+                    }
+                };
+                seen.insert(file.clone());
+                if !index.update(file.clone(), observed) || queue.is_processed(&file, observed) {
+                    continue;
+                }
+            }
+            let input = upload_input(file.clone(), configs, opts)?;
+            match upload::skip_existing(&file, &input, ctx) {
+                Ok(true) => (),
+                Ok(false) => queue.push(file),
+                Err(upload::Error::HttpClient { source }) => {
+                    log::warn!("Cannot check {}: {}", file.display(), source);
+                    queue.push(file);
+                }
+                Err(err) => return Err(Error::Upload { source: err }),
+            }
+        }
+    }
+    queue.save().context(QueueSnafu)?;
+    if let Some(index) = index {
+        index.finish(&seen).context(IndexSnafu)?;
+    }
+    Ok(())
+}
+
+/// Returns the size and modification time (in milliseconds) of a
+/// file.
+fn observe(path: &Path) -> Result<(u64, i64), std::io::Error> {
+    let meta = std::fs::metadata(path)?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    Ok((meta.len(), mtime))
+}
+
+/// Checks whether the file is complete. It is not, if a lock file
+/// exists for it or its size or modification time changed within the
+/// last `--stable-secs` seconds. In this case, it is checked again
+/// later.
+fn is_stable(path: &Path, queue: &mut UploadQueue, opts: &Input) -> bool {
+    let wait = Duration::from_secs(opts.stable_secs);
+    if let Some(lock) = lock_file(path) {
+        log::info!("Waiting for lock file {} to disappear", lock.display());
+        queue.defer(path, wait.max(Duration::from_secs(1)));
+        return false;
+    }
+    let observed = match observe(path) {
+        Ok(o) => o,
+        Err(err) => {
+            log::debug!("Cannot read metadata of {}: {}", path.display(), err);
+            queue.defer(path, wait.max(Duration::from_secs(1)));
+            return false;
+        }
+    };
+    let stable = queue.check_stable(path, observed, wait);
+    if !stable {
+        log::info!("Waiting for {} to become stable", path.display());
+    }
+    stable
+}
+
+/// Returns an existing lock file for the given file. Some programs
+/// create `<file>.lock`, LibreOffice creates `.~lock.<file>#`.
+fn lock_file(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy();
+    [format!("{}.lock", name), format!(".~lock.{}#", name)]
+        .into_iter()
+        .map(|lock| path.with_file_name(lock))
+        .find(|lock| lock.exists())
+}
+
+/// Checks whether the file name matches one of the `--ignore`
+/// patterns.
+fn is_ignored(path: &Path, opts: &Input) -> bool {
+    if upload::dirconfig::is_config_file(path)
+        || upload::sidecar::is_sidecar(path)
+        || upload::is_error_report(path)
+    {
+        return true;
+    }
+    let name = match path.file_name() {
+        Some(n) => n.to_string_lossy(),
+        None => return false,
+    };
+    for pattern in &opts.ignore {
+        if pattern.matches(&name) {
+            log::debug!("Ignoring {} matching {}", path.display(), pattern);
+            return true;
+        }
+    }
+    false
 }
 
 fn check_is_dir(dirs: &[PathBuf]) -> Result<(), Error> {
@@ -132,64 +591,159 @@ fn check_is_dir(dirs: &[PathBuf]) -> Result<(), Error> {
     Ok(())
 }
 
-fn event_act(event: DebouncedEvent, opts: &Input, ctx: &Context) -> Result<(), Error> {
+fn event_act(event: &DebouncedEvent, queue: &mut UploadQueue, opts: &Input) -> Result<(), Error> {
     log::info!("Event: {:?}", event);
-    match event {
-        DebouncedEvent::Create(path) => upload_and_report(path, opts, ctx)?,
-        DebouncedEvent::Write(path) => upload_and_report(path, opts, ctx)?,
-        DebouncedEvent::Chmod(path) => upload_and_report(path, opts, ctx)?,
-        DebouncedEvent::Error(err, path_opt) => {
-            log::error!("Debounce event error for path {:?}: {}", path_opt, err);
-            return Err(Error::Watch { source: err });
+    match event.kind {
+        EventKind::Create(_)
+        | EventKind::Modify(ModifyKind::Any)
+        | EventKind::Modify(ModifyKind::Data(_))
+        | EventKind::Modify(ModifyKind::Metadata(_)) => {
+            for path in &event.paths {
+                enqueue(path.clone(), queue, opts)?;
+            }
+        }
+        // A file or directory was moved into the tree or renamed within
+        // it. Only the target is of interest.
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            for path in &event.paths {
+                moved_in(path, queue, opts)?;
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            if let Some(path) = event.paths.last() {
+                moved_in(path, queue, opts)?;
+            }
+        }
+        // Some platforms don't tell the direction of a rename.
+        EventKind::Modify(ModifyKind::Name(RenameMode::Any | RenameMode::Other)) => {
+            for path in event.paths.iter().filter(|p| p.exists()) {
+                moved_in(path, queue, opts)?;
+            }
         }
         _ => (),
     }
     Ok(())
 }
 
-fn upload_and_report(path: PathBuf, opts: &Input, ctx: &Context) -> Result<(), Error> {
+/// Adds a file that has been moved into a watched directory to the
+/// queue. No events are generated for the files of a directory that
+/// is moved, so these are added as well when watching recursively.
+fn moved_in(path: &Path, queue: &mut UploadQueue, opts: &Input) -> Result<(), Error> {
+    if !path.is_dir() {
+        return enqueue(path.to_path_buf(), queue, opts);
+    }
+    if opts.recursive {
+        let matcher = upload::matching::Matcher::new(&opts.matches, &opts.not_matches)
+            .context(UploadSnafu)?;
+        for file in matcher.traverse(path).context(UploadSnafu)? {
+            enqueue(file, queue, opts)?;
+        }
+    }
+    Ok(())
+}
+
+fn enqueue(path: PathBuf, queue: &mut UploadQueue, opts: &Input) -> Result<(), Error> {
     if path.is_dir() {
         log::debug!(
             "Skip event triggered on a directory and not a file: {:?}",
             path
         );
+        Ok(())
+    } else if is_ignored(&path, opts) {
+        Ok(())
+    } else if is_unchanged(&path, queue) {
+        log::debug!(
+            "Skip {}, it didn't change since it was processed",
+            path.display()
+        );
+        Ok(())
     } else {
-        eprintln!("------------------------------------------------------------------------------");
-        eprintln!("Got: {}", path.display());
-        let result = upload_file(path, opts, ctx)?;
-        if result.success {
-            if opts.dry_run {
-                eprintln!("Dry run. Would upload now.");
-            } else {
-                eprintln!("Server: {}", result.message);
-            }
+        log::debug!("Adding to queue: {}", path.display());
+        queue.push(path);
+        Ok(())
+    }
+}
+
+/// Whether the file didn't change since it has been processed. Events
+/// for such a file are caused by processing it, for example by a
+/// pre-upload hook that changes the file in place.
+fn is_unchanged(path: &Path, queue: &UploadQueue) -> bool {
+    observe(path)
+        .map(|observed| queue.is_processed(path, observed))
+        .unwrap_or(false)
+}
+
+fn upload_and_report(
+    path: PathBuf,
+    configs: &DirConfigs,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<(), Error> {
+    eprintln!("------------------------------------------------------------------------------");
+    eprintln!("Got: {}", path.display());
+    let result = upload_file(path.clone(), configs, opts, ctx)?;
+    if result.success {
+        if opts.dry_run {
+            eprintln!("Dry run. Would upload now.");
         } else {
-            log::error!("Error from uploading: {}", result.message);
-            eprintln!("Sevrer Error: {}", result.message);
+            eprintln!("Server: {}", result.message);
+        }
+    } else {
+        log::error!("Error from uploading: {}", result.message);
+        eprintln!("Sevrer Error: {}", result.message);
+        if let Some(spec) = &opts.on_error {
+            let root = watched_dir(&path, opts);
+            upload::quarantine(&path, root, &result.message, spec, &opts.action)
+                .context(UploadSnafu)?;
         }
     }
     Ok(())
 }
 
-fn upload_file(path: PathBuf, opts: &Input, ctx: &Context) -> Result<BasicResult, Error> {
+fn upload_file(
+    path: PathBuf,
+    configs: &DirConfigs,
+    opts: &Input,
+    ctx: &Context,
+) -> Result<BasicResult, Error> {
+    let data = &upload_input(path, configs, opts)?;
+    upload::upload_files(data, ctx).context(UploadSnafu)
+}
+
+/// Creates the options to upload the given file, including the
+/// settings from directory config files.
+fn upload_input(path: PathBuf, configs: &DirConfigs, opts: &Input) -> Result<upload::Input, Error> {
     let mut ep = opts.endpoint.clone();
     if let Some(cid) = find_collective(&path, &opts.dirs, &opts.endpoint)? {
         ep.collective = Some(cid);
     }
+    let mut meta = opts.upload.clone();
+    let root = watched_dir(&path, opts);
+    configs
+        .resolve(&path, root.map(PathBuf::as_path))
+        .context(UploadSnafu)?
+        .apply(&mut meta, &mut ep);
 
-    let data = &upload::Input {
+    Ok(upload::Input {
         endpoint: ep,
         multiple: true,
         action: opts.action.clone(),
-        upload: opts.upload.clone(),
+        upload: meta,
         matches: opts.matches.clone(),
         not_matches: opts.not_matches.clone(),
         traverse: false,
         poll: None,
+        parallel: 1,
+        on_error: None,
+        max_attempts: opts.max_attempts,
         dry_run: opts.dry_run,
         files: vec![path],
-    };
-    upload::upload_files(data, ctx).context(UploadSnafu)
+    })
+}
+
+/// Returns the watched directory that contains the file.
+fn watched_dir<'a>(path: &Path, opts: &'a Input) -> Option<&'a PathBuf> {
+    opts.dirs.iter().find(|dir| path.starts_with(dir))
 }
 
 pub fn find_collective(
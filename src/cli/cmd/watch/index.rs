@@ -0,0 +1,121 @@
+//! An index of files seen by the polling watcher.
+//!
+//! For each file, the size and modification time are stored. When
+//! the directories are traversed again, only files that are not in
+//! the index or have a different size or modification time are
+//! considered. The index is stored in a file, so that this works
+//! across restarts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+/// The prefix of the default index file in the config directory.
+pub const INDEX_FILE: &str = "watch-index";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexEntry {
+    path: PathBuf,
+    size: u64,
+    mtime: i64,
+}
+
+pub struct PollIndex {
+    /// The file to store the index. If not present, the index is only
+    /// kept in memory.
+    file: Option<PathBuf>,
+    entries: HashMap<PathBuf, (u64, i64)>,
+}
+
+impl PollIndex {
+    pub fn load(file: Option<PathBuf>) -> Result<PollIndex, io::Error> {
+        let entries: Vec<IndexEntry> = match &file {
+            Some(f) if f.exists() => serde_json::from_reader(BufReader::new(File::open(f)?))?,
+            _ => Vec::new(),
+        };
+        log::debug!("Loaded {} entries from the poll index", entries.len());
+        Ok(PollIndex {
+            file,
+            entries: entries
+                .into_iter()
+                .map(|e| (e.path, (e.size, e.mtime)))
+                .collect(),
+        })
+    }
+
+    /// Returns the default location of the index file for the
+    /// watcher identified by `key`.
+    pub fn default_file(key: &str) -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("dsc").join(format!("{}-{}.json", INDEX_FILE, key)))
+    }
+
+    /// Records the size and modification time of a file. Returns
+    /// whether the file is new or changed since it was recorded last.
+    pub fn update(&mut self, path: PathBuf, observed: (u64, i64)) -> bool {
+        self.entries.insert(path, observed) != Some(observed)
+    }
+
+    /// Removes all files that have not been seen in the last
+    /// traversal and stores the index.
+    pub fn finish(&mut self, seen: &HashSet<PathBuf>) -> Result<(), io::Error> {
+        self.entries.retain(|path, _| seen.contains(path));
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), io::Error> {
+        if let Some(file) = &self.file {
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut entries: Vec<IndexEntry> = self
+                .entries
+                .iter()
+                .map(|(path, (size, mtime))| IndexEntry {
+                    path: path.clone(),
+                    size: *size,
+                    mtime: *mtime,
+                })
+                .collect();
+            entries.sort_by(|a, b| a.path.cmp(&b.path));
+            let tmp = file.with_extension("json.tmp");
+            {
+                let mut out = BufWriter::new(File::create(&tmp)?);
+                serde_json::to_writer(&mut out, &entries)?;
+                out.flush()?;
+            }
+            std::fs::rename(&tmp, file)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_poll_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("index.json");
+        let a = PathBuf::from("/tmp/a.pdf");
+        let b = PathBuf::from("/tmp/b.pdf");
+
+        let mut index = PollIndex::load(Some(file.clone())).unwrap();
+        assert!(index.update(a.clone(), (10, 1)));
+        assert!(index.update(b.clone(), (20, 1)));
+        index.finish(&[a.clone(), b.clone()].into()).unwrap();
+
+        // unchanged files are skipped after a restart
+        let mut index = PollIndex::load(Some(file.clone())).unwrap();
+        assert!(!index.update(a.clone(), (10, 1)));
+        assert!(index.update(b.clone(), (20, 2)));
+        // files not seen anymore are forgotten
+        index.finish(&[b.clone()].into()).unwrap();
+
+        let mut index = PollIndex::load(Some(file)).unwrap();
+        assert!(index.update(a, (10, 1)));
+        assert!(!index.update(b, (20, 2)));
+    }
+}
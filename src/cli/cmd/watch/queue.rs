@@ -0,0 +1,278 @@
+//! A persistent queue of files waiting to be uploaded.
+//!
+//! Files detected by `watch` are put into this queue first. If an
+//! upload fails, because the server is not reachable for example,
+//! the file stays in the queue and is tried again later with an
+//! increasing delay. The queue is stored in a file, so that pending
+//! uploads are not lost when the watcher is restarted.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The prefix of the default queue file in the config directory.
+pub const QUEUE_FILE: &str = "watch-queue";
+
+/// The delay before the first retry. It is doubled for every
+/// following one.
+const BACKOFF: Duration = Duration::from_secs(5);
+
+/// The maximum delay between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub path: PathBuf,
+    pub attempts: u32,
+    /// The earliest time of the next attempt, in milliseconds since
+    /// the epoch.
+    pub next_attempt: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// The size and modification time (in milliseconds) of the file
+    /// when it was last checked for stability.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed: Option<(u64, i64)>,
+}
+
+pub struct UploadQueue {
+    /// The file to store the queue. If not present, the queue is only
+    /// kept in memory.
+    file: Option<PathBuf>,
+    entries: HashMap<PathBuf, QueueEntry>,
+    /// Whether the entries changed since the queue has been saved.
+    dirty: bool,
+    /// The size and modification time of files that have been
+    /// processed and are still in place. Changes made while processing
+    /// a file, like by a pre-upload hook, don't add it again.
+    processed: HashMap<PathBuf, (u64, i64)>,
+}
+
+impl UploadQueue {
+    /// Loads the queue from the given file. All entries are due
+    /// immediately, so they are retried right after a restart.
+    pub fn load(file: Option<PathBuf>) -> Result<UploadQueue, io::Error> {
+        let entries: Vec<QueueEntry> = match &file {
+            Some(f) if f.exists() => serde_json::from_reader(BufReader::new(File::open(f)?))?,
+            _ => Vec::new(),
+        };
+        let entries = entries
+            .into_iter()
+            .map(|entry| {
+                let entry = QueueEntry {
+                    next_attempt: 0,
+                    ..entry
+                };
+                (entry.path.clone(), entry)
+            })
+            .collect();
+        Ok(UploadQueue {
+            file,
+            entries,
+            dirty: false,
+            processed: HashMap::new(),
+        })
+    }
+
+    /// Returns the default location of the queue file for the
+    /// watcher identified by `key`.
+    pub fn default_file(key: &str) -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("dsc").join(format!("{}-{}.json", QUEUE_FILE, key)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds a file to the queue. If it is already queued, it keeps
+    /// waiting for its next attempt.
+    pub fn push(&mut self, path: PathBuf) {
+        if !self.entries.contains_key(&path) {
+            self.entries.insert(
+                path.clone(),
+                QueueEntry {
+                    path,
+                    attempts: 0,
+                    next_attempt: 0,
+                    last_error: None,
+                    observed: None,
+                },
+            );
+            self.dirty = true;
+        }
+    }
+
+    /// Returns all files that are due for an upload, sorted by path.
+    pub fn due(&self) -> Vec<PathBuf> {
+        let now = Utc::now().timestamp_millis();
+        let mut due: Vec<PathBuf> = self
+            .entries
+            .values()
+            .filter(|e| e.next_attempt <= now)
+            .map(|e| e.path.clone())
+            .collect();
+        due.sort();
+        due
+    }
+
+    /// Returns how long it takes until the next entry is due.
+    pub fn next_due(&self) -> Option<Duration> {
+        let now = Utc::now().timestamp_millis();
+        self.entries
+            .values()
+            .map(|e| Duration::from_millis((e.next_attempt - now).max(0) as u64))
+            .min()
+    }
+
+    /// Removes a file from the queue, after it has been uploaded or
+    /// if it cannot be uploaded at all.
+    pub fn remove(&mut self, path: &Path) {
+        if self.entries.remove(path).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Removes a file from the queue after it has been processed. If
+    /// it still exists, its current size and modification time are
+    /// remembered.
+    pub fn processed(&mut self, path: &Path, observed: Option<(u64, i64)>) {
+        match observed {
+            Some(observed) => self.processed.insert(path.to_path_buf(), observed),
+            None => self.processed.remove(path),
+        };
+        self.remove(path)
+    }
+
+    /// Returns whether the file didn't change since it has been
+    /// processed.
+    pub fn is_processed(&self, path: &Path, observed: (u64, i64)) -> bool {
+        self.processed.get(path) == Some(&observed)
+    }
+
+    /// Checks whether a file didn't change since it was last looked
+    /// at. If it did change or is seen for the first time, its size
+    /// and modification time are recorded and the entry is deferred by
+    /// `wait`. A file is stable immediately, if `wait` is zero.
+    pub fn check_stable(&mut self, path: &Path, observed: (u64, i64), wait: Duration) -> bool {
+        if wait.is_zero() {
+            return true;
+        }
+        match self.entries.get_mut(path) {
+            Some(entry) if entry.observed != Some(observed) => {
+                entry.observed = Some(observed);
+                entry.next_attempt = Utc::now().timestamp_millis() + wait.as_millis() as i64;
+                self.dirty = true;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Defers an entry by the given duration.
+    pub fn defer(&mut self, path: &Path, wait: Duration) {
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.next_attempt = Utc::now().timestamp_millis() + wait.as_millis() as i64;
+            self.dirty = true;
+        }
+    }
+
+    /// Returns the number of failed attempts to upload the file.
+    pub fn attempts(&self, path: &Path) -> u32 {
+        self.entries.get(path).map(|e| e.attempts).unwrap_or(0)
+    }
+
+    /// Records a failed attempt and returns the delay until the next
+    /// one.
+    pub fn failed(&mut self, path: &Path, error: String) -> Duration {
+        let mut delay = BACKOFF;
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.attempts += 1;
+            delay = backoff(entry.attempts);
+            entry.next_attempt = Utc::now().timestamp_millis() + delay.as_millis() as i64;
+            entry.last_error = Some(error);
+            self.dirty = true;
+        }
+        delay
+    }
+
+    /// Writes the queue to its file, if it changed. Changes are kept in
+    /// memory until then, so this is called after a batch of changes,
+    /// like a scan or processing the due files.
+    pub fn save(&mut self) -> Result<(), io::Error> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(file) = &self.file {
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut entries: Vec<&QueueEntry> = self.entries.values().collect();
+            entries.sort_by(|a, b| a.path.cmp(&b.path));
+            let tmp = file.with_extension("json.tmp");
+            {
+                let mut out = BufWriter::new(File::create(&tmp)?);
+                serde_json::to_writer_pretty(&mut out, &entries)?;
+                out.flush()?;
+            }
+            std::fs::rename(&tmp, file)?;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// Returns the delay after the given number of failed attempts.
+fn backoff(attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    BACKOFF.saturating_mul(factor).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_upload_queue_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("queue.json");
+
+        let mut queue = UploadQueue::load(Some(file.clone())).unwrap();
+        queue.push(PathBuf::from("/tmp/a.pdf"));
+        queue.push(PathBuf::from("/tmp/b.pdf"));
+        let delay = queue.failed(Path::new("/tmp/a.pdf"), "down".into());
+        assert_eq!(delay, BACKOFF);
+        assert_eq!(queue.attempts(Path::new("/tmp/a.pdf")), 1);
+        // pushing it again keeps the backoff
+        queue.push(PathBuf::from("/tmp/a.pdf"));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.due(), vec![PathBuf::from("/tmp/b.pdf")]);
+        queue.remove(Path::new("/tmp/b.pdf"));
+        assert!(!file.exists());
+        queue.save().unwrap();
+
+        let mut queue = UploadQueue::load(Some(file.clone())).unwrap();
+        assert_eq!(queue.due(), vec![PathBuf::from("/tmp/a.pdf")]);
+        let a = Path::new("/tmp/a.pdf");
+        let wait = Duration::from_secs(10);
+        assert!(!queue.check_stable(a, (10, 1), wait));
+        assert!(queue.due().is_empty());
+        assert!(queue.check_stable(a, (10, 1), wait));
+        assert!(!queue.check_stable(a, (12, 2), wait));
+        queue.processed(a, Some((14, 3)));
+        assert!(queue.is_processed(a, (14, 3)));
+        assert!(!queue.is_processed(a, (14, 4)));
+        assert!(queue.due().is_empty());
+        queue.save().unwrap();
+        assert!(UploadQueue::load(Some(file)).unwrap().is_empty());
+        assert_eq!(backoff(3), BACKOFF * 4);
+        assert_eq!(backoff(30), MAX_BACKOFF);
+    }
+}
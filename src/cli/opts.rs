@@ -7,6 +7,7 @@ use crate::{
     http::proxy,
     http::{FileAuth, IntegrationAuth, IntegrationData},
 };
+use chrono::format::{Item, StrftimeItems};
 use clap::{ArgAction, ArgGroup, Parser, ValueEnum, ValueHint};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
@@ -97,6 +98,24 @@ pub struct CommonOpts {
     /// specfified.
     #[arg(long, group = "tls")]
     pub accept_invalid_certificates: bool,
+
+    /// How often a request is attempted when it fails due to a
+    /// connection error, a timeout or a retryable status code. Use 1
+    /// to disable retries. (Defaults to 3)
+    #[arg(long)]
+    pub retry_attempts: Option<u32>,
+
+    /// The delay in milliseconds before retrying a failed request. It
+    /// is doubled for each further retry. (Defaults to 500)
+    #[arg(long)]
+    pub retry_backoff: Option<u64>,
+
+    /// A comma separated list of http status codes that cause a
+    /// request to be retried. Requests that change data, like
+    /// uploads, are only retried on connection errors. (Defaults to
+    /// 429,502,503,504, add 500 to retry on internal server errors)
+    #[arg(long, value_delimiter = ',')]
+    pub retry_status: Option<Vec<u16>>,
 }
 
 impl CommonOpts {
@@ -201,11 +220,23 @@ pub enum SubCommand {
     #[command(version)]
     Export(export::Input),
 
+    #[command(version)]
+    Import(import::Input),
+
     #[command(version)]
     Admin(admin::Input),
 
     #[command(version)]
     OpenItem(open_item::Input),
+
+    #[command(version)]
+    Addon(addon::Input),
+
+    #[command(version)]
+    ClientSettings(client_settings::Input),
+
+    #[command(version)]
+    Classifier(classifier::Input),
 }
 
 /// The format for presenting the results.
@@ -334,12 +365,12 @@ impl EndpointOpts {
                     "Reading file for integration header {}",
                     header_file.display()
                 );
-                let np = Self::read_name_val(&header_file)?;
+                let np = Self::read_name_val(header_file)?;
                 res.auth = IntegrationAuth::Header(np.name.clone(), np.value.clone());
             }
             if let Some(basic_file) = &self.basic_file {
                 log::debug!("Reading file for basic auth {}", basic_file.display());
-                let np = Self::read_name_val(&basic_file)?;
+                let np = Self::read_name_val(basic_file)?;
                 res.auth = IntegrationAuth::Basic(np.name.clone(), np.value.clone());
             }
             if let Some(basic) = &self.basic {
@@ -386,9 +417,12 @@ impl std::str::FromStr for NameVal {
 }
 
 /// The direction of an item in docspell.
-#[derive(ValueEnum, Debug, Clone)]
+#[derive(ValueEnum, Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction {
+    #[serde(alias = "incoming")]
     In,
+    #[serde(alias = "outgoing")]
     Out,
 }
 impl Direction {
@@ -445,15 +479,98 @@ pub struct UploadMeta {
 // Shared options for specifying what to do with a file.
 #[derive(Parser, Debug, Clone)]
 #[command(group = ArgGroup::new("file-action"))]
+#[command(group = ArgGroup::new("upload-target").multiple(true).args(["move_to", "copy_to"]))]
+#[command(group = ArgGroup::new("file-target")
+    .multiple(true)
+    .args(["move_to", "copy_to", "on_duplicate"]))]
 pub struct FileAction {
     /// Deletes the file.
     #[arg(long, group = "file-action")]
     pub delete: bool,
 
     /// Moves the file into the given directory. The directory
-    /// structure is retained in the target folder.
+    /// structure is retained in the target folder. If a file with the
+    /// same name exists there, a counter is added to the name.
     #[arg(long = "move", group = "file-action", value_hint = ValueHint::DirPath)]
     pub move_to: Option<PathBuf>,
+
+    /// Copies the file into the given directory and keeps the
+    /// original. The directory structure is retained in the target
+    /// folder. If a file with the same name exists there, a counter
+    /// is added to the name.
+    #[arg(long = "copy", group = "file-action", value_hint = ValueHint::DirPath)]
+    pub copy_to: Option<PathBuf>,
+
+    /// When moving or copying uploaded files, puts them into a
+    /// subdirectory of the target named after the current date using
+    /// this format, for example `%Y/%m`. Files moved or copied by
+    /// `--on-duplicate` or `--on-error` go into such a subdirectory of
+    /// their target as well.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        requires = "upload-target",
+        value_parser = parse_date_format
+    )]
+    pub date_dir: Option<String>,
+
+    /// When moving or copying, appends the id of the item to the file
+    /// name, like `scan_<id>.pdf`. For uploaded files, this waits
+    /// until the server has processed them.
+    #[arg(long, requires = "file-target")]
+    pub rename_with_id: bool,
+
+    /// How many seconds to wait for the server to process an uploaded
+    /// file, if the id of its item is needed for `--rename-with-id`
+    /// or a sidecar file. Meanwhile no other file is uploaded, except
+    /// by the other workers of `--parallel`.
+    #[arg(long, value_name = "SECS", default_value_t = 300)]
+    pub process_timeout: u64,
+
+    /// The action for files that are already in Docspell: `keep`,
+    /// `delete`, `move:<dir>` or `copy:<dir>`. If not given, the same
+    /// action as for uploaded files is used.
+    #[arg(long, value_name = "ACTION")]
+    pub on_duplicate: Option<ActionSpec>,
+}
+
+/// An action to apply to a file, given as `keep`, `delete`,
+/// `move:<dir>` or `copy:<dir>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionSpec {
+    Keep,
+    Delete,
+    Move(PathBuf),
+    Copy(PathBuf),
+}
+
+impl FromStr for ActionSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "keep" => Ok(ActionSpec::Keep),
+            None if s == "delete" => Ok(ActionSpec::Delete),
+            Some(("move", dir)) if !dir.is_empty() => Ok(ActionSpec::Move(PathBuf::from(dir))),
+            Some(("copy", dir)) if !dir.is_empty() => Ok(ActionSpec::Copy(PathBuf::from(dir))),
+            _ => Err(format!(
+                "Invalid action '{}', use one of: keep, delete, move:<dir>, copy:<dir>",
+                s
+            )),
+        }
+    }
+}
+
+/// Checks that the `--date-dir` format can be used to format a date.
+fn parse_date_format(s: &str) -> Result<String, String> {
+    if StrftimeItems::new(s).any(|item| matches!(item, Item::Error)) {
+        Err(format!(
+            "Invalid date format '{}', use for example %Y/%m",
+            s
+        ))
+    } else {
+        Ok(s.to_string())
+    }
 }
 
 #[derive(Parser, Debug, Clone)]
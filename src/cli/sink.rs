@@ -26,10 +26,7 @@ where
 {
     fn write_value(format: Format, value: &Self) -> Result<(), Error> {
         match format {
-            Format::Json => {
-                serde_json::to_writer(std::io::stdout(), &value)?;
-                Ok(())
-            }
+            Format::Json => Self::write_json(value),
             Format::Lisp => {
                 serde_lexpr::to_writer(std::io::stdout(), &value)?;
                 Ok(())
@@ -44,6 +41,11 @@ where
         }
     }
 
+    fn write_json(value: &Self) -> Result<(), Error> {
+        serde_json::to_writer(std::io::stdout(), &value)?;
+        Ok(())
+    }
+
     fn write_tabular(value: &Self) -> Result<(), Error> {
         let table = value.to_table();
         table.printstd();
@@ -127,11 +127,90 @@ impl AsTable for ItemDetail {
             "Attachments Archives",
             format!("{}", self.archives.len())
         ]);
+        let related_list: Vec<String> = self
+            .related
+            .iter()
+            .map(|r| format!("{} {}", r.id.get(..8).unwrap_or(&r.id), r.name))
+            .collect();
+        table.add_row(row!["Related", related_list.join(", ")]);
         table
     }
 }
 impl Sink for ItemDetail {}
 
+/// Joins the names of the given [`IdName`] objects.
+fn join_names(names: &[IdName]) -> String {
+    names
+        .iter()
+        .map(|n| n.name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// Joins the given unix timestamps formatted as "year-month-day".
+fn join_dates(dates: &[i64]) -> String {
+    dates
+        .iter()
+        .map(|d| format_date(*d))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl AsTable for ItemProposals {
+    fn to_table(&self) -> Table {
+        let mut table = mk_table();
+        table.set_titles(row![bFg => "Property", "Proposals"]);
+        table.add_row(row!["Correspondent (org)", join_names(&self.corr_org)]);
+        table.add_row(row![
+            "Correspondent (person)",
+            join_names(&self.corr_person)
+        ]);
+        table.add_row(row!["Concerning (person)", join_names(&self.conc_person)]);
+        table.add_row(row!["Concerning (equipment)", join_names(&self.conc_equip)]);
+        table.add_row(row!["Date", join_dates(&self.item_date)]);
+        table.add_row(row!["Due", join_dates(&self.due_date)]);
+        table
+    }
+}
+impl Sink for ItemProposals {}
+
+impl AsTable for ItemProposalsList {
+    fn to_table(&self) -> Table {
+        let mut table = mk_table();
+        table.set_titles(row![bFg =>
+            "id",
+            "name",
+            "corr. org",
+            "corr. person",
+            "conc. person",
+            "conc. equipment",
+            "date",
+            "due"
+        ]);
+        for entry in &self.items {
+            let p = &entry.proposals;
+            let first_name = |names: &[IdName]| {
+                names
+                    .first()
+                    .map(|n| n.name.clone())
+                    .unwrap_or_else(|| "".into())
+            };
+            table.add_row(row![
+                entry.id.get(..8).unwrap_or(&entry.id),
+                entry.name,
+                first_name(&p.corr_org),
+                first_name(&p.corr_person),
+                first_name(&p.conc_person),
+                first_name(&p.conc_equip),
+                format_date_opt(&p.item_date.first().copied()),
+                format_date_opt(&p.due_date.first().copied()),
+            ]);
+        }
+        table
+    }
+}
+impl Sink for ItemProposalsList {}
+
 impl AsTable for Item {
     fn to_table(&self) -> Table {
         let mut table = mk_table();
@@ -433,3 +512,86 @@ impl AsTable for SearchResult {
     }
 }
 impl Sink for SearchResult {}
+
+impl AsTable for AddonList {
+    fn to_table(&self) -> Table {
+        let mut table = mk_table();
+        table.set_titles(row![bFg => "id", "name", "version", "description", "url"]);
+        for addon in &self.items {
+            table.add_row(row![
+                addon.id.get(..8).unwrap_or(&addon.id),
+                addon.name,
+                addon.version,
+                str_or_empty(addon.description.as_ref()),
+                str_or_empty(addon.url.as_ref())
+            ]);
+        }
+        table
+    }
+}
+impl Sink for AddonList {}
+
+impl AsTable for AddonRunConfigList {
+    fn to_table(&self) -> Table {
+        let mut table = mk_table();
+        table.set_titles(row![bFg => "id", "name", "enabled", "trigger", "schedule", "addons"]);
+        for cfg in &self.items {
+            let addons: Vec<&str> = cfg.addons.iter().map(|a| a.name.as_str()).collect();
+            table.add_row(row![
+                cfg.id.get(..8).unwrap_or(&cfg.id),
+                cfg.name,
+                cfg.enabled,
+                cfg.trigger.join(", "),
+                str_or_empty(cfg.schedule.as_ref()),
+                addons.join(", ")
+            ]);
+        }
+        table
+    }
+}
+impl Sink for AddonRunConfigList {}
+
+impl AsTable for IdResult {
+    fn to_table(&self) -> Table {
+        let mut table = mk_table();
+        table.set_titles(row![bFg => "success", "id", "message"]);
+        table.add_row(row![self.success, self.id, self.message]);
+        table
+    }
+}
+impl Sink for IdResult {}
+
+impl AsTable for ClassifierSetting {
+    fn to_table(&self) -> Table {
+        let mut table = mk_table();
+        table.set_titles(row![bFg => "schedule", "item count", "list type", "categories"]);
+        table.add_row(row![
+            self.schedule,
+            self.item_count,
+            self.list_type,
+            self.category_list.join(", ")
+        ]);
+        table
+    }
+}
+impl Sink for ClassifierSetting {}
+
+impl AsTable for ClientSettings {
+    fn to_table(&self) -> Table {
+        let mut table = mk_table();
+        table.set_titles(row![bFg => "key", "value"]);
+        if let Some(obj) = self.0.as_object() {
+            for (key, value) in obj {
+                table.add_row(row![key, value]);
+            }
+        }
+        table
+    }
+}
+impl Sink for ClientSettings {
+    fn write_json(value: &Self) -> Result<(), SinkError> {
+        serde_json::to_writer_pretty(std::io::stdout(), value)?;
+        println!();
+        Ok(())
+    }
+}
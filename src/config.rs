@@ -50,6 +50,30 @@ pub struct DsConfig {
 
     /// Whether to accept invalid certificates.
     pub accept_invalid_certificates: Option<bool>,
+
+    /// How often a request is attempted when it fails due to a
+    /// transient error. Use 1 to disable retries.
+    pub retry_attempts: Option<u32>,
+
+    /// The delay in milliseconds before the first retry. It is
+    /// doubled for each further retry.
+    pub retry_backoff_millis: Option<u64>,
+
+    /// Response status codes that cause a request to be retried.
+    pub retry_status_codes: Option<Vec<u16>>,
+
+    /// A command run before a file is uploaded. If it fails, the file
+    /// is not uploaded.
+    pub pre_upload_hook: Option<Vec<String>>,
+
+    /// A command run after a file has been uploaded.
+    pub post_upload_hook: Option<Vec<String>>,
+
+    /// A command run for files that are already in Docspell.
+    pub on_duplicate_hook: Option<Vec<String>>,
+
+    /// A command run for files that failed to upload.
+    pub on_error_hook: Option<Vec<String>>,
 }
 
 /// Error states when reading and writing the config file.
@@ -95,6 +119,13 @@ impl default::Default for DsConfig {
             proxy_password: None,
             extra_certificate: None,
             accept_invalid_certificates: None,
+            retry_attempts: None,
+            retry_backoff_millis: None,
+            retry_status_codes: None,
+            pre_upload_hook: None,
+            post_upload_hook: None,
+            on_duplicate_hook: None,
+            on_error_hook: None,
         }
     }
 }
@@ -207,7 +238,7 @@ fn write_to(cfg: &DsConfig, file: &Path) -> Result<(), ConfigError> {
     });
 
     cnt.and_then(|c| {
-        std::fs::write(&file, &c).map_err(|e| ConfigError::CreateDefault {
+        std::fs::write(file, &c).map_err(|e| ConfigError::CreateDefault {
             source: e,
             path: file.to_path_buf(),
         })
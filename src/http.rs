@@ -42,6 +42,7 @@
 
 pub mod payload;
 pub mod proxy;
+pub mod retry;
 mod session;
 mod util;
 
@@ -52,6 +53,7 @@ use std::{
 };
 
 use self::payload::*;
+use self::retry::{RetryPolicy, SendRetry, Transient};
 use self::util::{DOCSPELL_ADMIN, DOCSPELL_AUTH};
 use reqwest::blocking::{
     multipart::{Form, Part},
@@ -59,6 +61,7 @@ use reqwest::blocking::{
 };
 use reqwest::header::CONTENT_DISPOSITION;
 use reqwest::{Certificate, StatusCode};
+use serde::Serialize;
 use snafu::{ResultExt, Snafu};
 
 const APP_JSON: &str = "application/json";
@@ -106,6 +109,24 @@ pub enum Error {
 
     #[snafu(display("Item id not unique: {}", id))]
     ItemNotUnique { id: String },
+
+    #[snafu(display("Addons are not available at {}. Are they enabled?", url))]
+    AddonsUnavailable { url: String },
+
+    #[snafu(display("No addon found for: {}", id))]
+    AddonNotFound { id: String },
+
+    #[snafu(display("Addon not unique: {}", id))]
+    AddonNotUnique { id: String },
+
+    #[snafu(display("Installing addon failed: {}", message))]
+    AddonInstall { message: String },
+
+    #[snafu(display("No addon run configuration found for: {}", id))]
+    RunConfigNotFound { id: String },
+
+    #[snafu(display("Addon run configuration not unique: {}", id))]
+    RunConfigNotUnique { id: String },
 }
 
 /// The docspell http client.
@@ -120,6 +141,7 @@ pub enum Error {
 pub struct Client {
     client: reqwest::blocking::Client,
     base_url: String,
+    retry: RetryPolicy,
 }
 
 impl Client {
@@ -163,15 +185,28 @@ impl Client {
         Ok(Client {
             client,
             base_url: url,
+            retry: RetryPolicy::default(),
         })
     }
 
+    /// Sets the policy for retrying requests that failed due to
+    /// transient errors.
+    pub fn with_retry(self, retry: RetryPolicy) -> Client {
+        Client { retry, ..self }
+    }
+
+    /// Whether a request that failed with the given error may succeed
+    /// when trying again later, according to the retry policy.
+    pub fn is_transient(&self, err: &Error) -> bool {
+        self.retry.is_transient(err)
+    }
+
     /// Queries the Docspell server for its version and build information.
     pub fn version(&self) -> Result<VersionInfo, Error> {
         let url = &format!("{}/api/info/version", self.base_url);
         self.client
             .get(url)
-            .send()
+            .send_retry(&self.retry)
             .context(HttpSnafu { url })?
             .json::<VersionInfo>()
             .context(SerializeRespSnafu)
@@ -192,7 +227,7 @@ impl Client {
             .client
             .post(url)
             .json(req)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<AuthResp>()
@@ -226,7 +261,7 @@ impl Client {
             .client
             .post(url)
             .json(&req)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<AuthResp>()
@@ -254,7 +289,7 @@ impl Client {
             .client
             .post(url)
             .header(DOCSPELL_AUTH, token)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<AuthResp>()
@@ -287,7 +322,7 @@ impl Client {
                 ("q", &req.query),
                 ("searchMode", &req.search_mode.as_str().to_string()),
             ])
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<SearchResult>()
@@ -309,7 +344,7 @@ impl Client {
             .get(url)
             .header(DOCSPELL_AUTH, token)
             .query(&[("q", &query.into())])
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<Summary>()
@@ -326,7 +361,7 @@ impl Client {
         self.client
             .get(url)
             .header(DOCSPELL_AUTH, token)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<SourceList>()
@@ -342,7 +377,7 @@ impl Client {
             .get(url)
             .header(DOCSPELL_AUTH, token)
             .query(&[("q", query)])
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<TagList>()
@@ -357,7 +392,7 @@ impl Client {
             .client
             .get(url)
             .header(DOCSPELL_AUTH, token)
-            .send()
+            .send_retry(&self.retry)
             .context(HttpSnafu { url })?;
 
         resp.error_for_status()
@@ -385,7 +420,7 @@ impl Client {
                 .client
                 .get(url)
                 .header(DOCSPELL_AUTH, token)
-                .send()
+                .send_retry(&self.retry)
                 .context(HttpSnafu { url })?;
 
             if resp.status() == StatusCode::NOT_FOUND {
@@ -421,7 +456,7 @@ impl Client {
             .put(url)
             .header(DOCSPELL_AUTH, token)
             .json(tags)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -446,7 +481,7 @@ impl Client {
             .put(url)
             .header(DOCSPELL_AUTH, token)
             .json(tags)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -471,7 +506,7 @@ impl Client {
             .post(url)
             .header(DOCSPELL_AUTH, token)
             .json(tags)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -492,7 +527,7 @@ impl Client {
             .put(url)
             .header(DOCSPELL_AUTH, token)
             .json(fvalue)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -515,13 +550,241 @@ impl Client {
         self.client
             .delete(url)
             .header(DOCSPELL_AUTH, token)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
             .context(SerializeRespSnafu)
     }
 
+    /// Returns the proposals Docspell computed for the given item.
+    /// These are suggestions for correspondents, concerned entities
+    /// and dates, ordered by relevance.
+    pub fn get_item_proposals<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+    ) -> Result<ItemProposals, Error> {
+        let item_id = self.require_item_id(token, id, SearchMode::All)?;
+        let url = &format!("{}/api/v1/sec/item/{}/proposals", self.base_url, item_id);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .get(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<ItemProposals>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Sets the correspondent organization of the given item. The id
+    /// may be given abbreviated as a prefix.
+    pub fn set_corr_org<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        org: &OptionalId,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "corrOrg", org)
+    }
+
+    /// Sets the correspondent person of the given item. The id may be
+    /// given abbreviated as a prefix.
+    pub fn set_corr_person<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        person: &OptionalId,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "corrPerson", person)
+    }
+
+    /// Sets the concerning person of the given item. The id may be
+    /// given abbreviated as a prefix.
+    pub fn set_conc_person<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        person: &OptionalId,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "concPerson", person)
+    }
+
+    /// Sets the concerning equipment of the given item. The id may be
+    /// given abbreviated as a prefix.
+    pub fn set_conc_equip<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        equip: &OptionalId,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "concEquipment", equip)
+    }
+
+    /// Sets the date of the given item. The id may be given
+    /// abbreviated as a prefix.
+    pub fn set_item_date<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        date: &OptionalDate,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "date", date)
+    }
+
+    /// Sets the due date of the given item. The id may be given
+    /// abbreviated as a prefix.
+    pub fn set_due_date<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        date: &OptionalDate,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "duedate", date)
+    }
+
+    /// Sets the name of the given item. The id may be given
+    /// abbreviated as a prefix.
+    pub fn set_item_name<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        name: &OptionalText,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "name", name)
+    }
+
+    /// Sets the notes of the given item. The id may be given
+    /// abbreviated as a prefix.
+    pub fn set_item_notes<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        notes: &OptionalText,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "notes", notes)
+    }
+
+    /// Sets the folder of the given item. The id may be given
+    /// abbreviated as a prefix.
+    pub fn set_folder<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        folder: &OptionalId,
+    ) -> Result<BasicResult, Error> {
+        self.update_item(token, id, "folder", folder)
+    }
+
+    /// Finds a folder by its exact name.
+    pub fn find_folder(&self, token: &Option<String>, name: &str) -> Result<Option<IdName>, Error> {
+        self.find_by_name(token, "folder", name)
+    }
+
+    /// Finds an organization by its exact name.
+    pub fn find_organization(
+        &self,
+        token: &Option<String>,
+        name: &str,
+    ) -> Result<Option<IdName>, Error> {
+        self.find_by_name(token, "organization", name)
+    }
+
+    /// Finds a person by its exact name.
+    pub fn find_person(&self, token: &Option<String>, name: &str) -> Result<Option<IdName>, Error> {
+        self.find_by_name(token, "person", name)
+    }
+
+    /// Finds an equipment by its exact name.
+    pub fn find_equipment(
+        &self,
+        token: &Option<String>,
+        name: &str,
+    ) -> Result<Option<IdName>, Error> {
+        self.find_by_name(token, "equipment", name)
+    }
+
+    /// Creates a new tag.
+    pub fn create_tag(
+        &self,
+        token: &Option<String>,
+        name: &str,
+        category: Option<&String>,
+    ) -> Result<BasicResult, Error> {
+        let url = &format!("{}/api/v1/sec/tag", self.base_url);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        let tag = Tag {
+            id: "".into(),
+            name: name.to_string(),
+            category: category.cloned(),
+            created: 0,
+        };
+        self.client
+            .post(url)
+            .header(DOCSPELL_AUTH, token)
+            .json(&tag)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Returns all items that are related to the given item. The id
+    /// may be given abbreviated as a prefix.
+    ///
+    /// If the server doesn't support related items, an empty list is
+    /// returned.
+    pub fn get_related_items<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+    ) -> Result<Vec<Item>, Error> {
+        let item_id = self.require_item_id(token, id, SearchMode::All)?;
+        let url = &format!("{}/api/v1/sec/itemlink/{}", self.base_url, item_id);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        let resp = self
+            .client
+            .get(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
+            .context(HttpSnafu { url })?;
+
+        if resp.status() == StatusCode::NOT_FOUND {
+            log::debug!("No related items found at {}", url);
+            Ok(vec![])
+        } else {
+            resp.error_for_status()
+                .context(HttpSnafu { url })?
+                .json::<Group>()
+                .context(SerializeRespSnafu)
+                .map(|g| g.items)
+        }
+    }
+
+    /// Relates the given items to the item with the given id. All ids
+    /// may be given abbreviated as a prefix.
+    pub fn link_items<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        related: &[String],
+    ) -> Result<BasicResult, Error> {
+        self.post_item_links(token, id, related, "addAll")
+    }
+
+    /// Removes the relation of the given items to the item with the
+    /// given id. All ids may be given abbreviated as a prefix.
+    pub fn unlink_items<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        related: &[String],
+    ) -> Result<BasicResult, Error> {
+        self.post_item_links(token, id, related, "removeAll")
+    }
+
     /// Given a search query, returns an iterator over all attachments
     /// of the results. The attachments can be downloaded by calling
     /// the corresponding functions on the iterators elements.
@@ -564,7 +827,7 @@ impl Client {
         let resp = data
             .auth
             .apply(rb)
-            .send()
+            .send_retry(&self.retry)
             .context(HttpSnafu { url: url.clone() })?;
         match resp.status() {
             StatusCode::NOT_FOUND => Ok(false),
@@ -589,7 +852,7 @@ impl Client {
         self.client
             .post(url)
             .json(req)
-            .send()
+            .send_retry(&self.retry)
             .context(HttpSnafu { url })?
             .json::<InviteResult>()
             .context(SerializeRespSnafu)
@@ -602,7 +865,7 @@ impl Client {
         self.client
             .post(url)
             .json(req)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -641,7 +904,7 @@ impl Client {
         let rb = self.client.get(&url);
         file_auth
             .apply(self, rb)?
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<CheckFileResult>()
@@ -675,34 +938,350 @@ impl Client {
         };
 
         let meta_json = serde_json::to_vec(&meta).context(SerializeReqSnafu)?;
-        let meta_part = Part::bytes(meta_json)
-            .mime_str(APP_JSON)
-            .context(MimeSnafu { raw: APP_JSON })?;
-        let mut form = Form::new().part("meta", meta_part);
-        for path in files {
-            log::debug!("Adding to request: {}", path.display());
-
-            let fopen = File::open(path).context(OpenFileSnafu { path })?;
-            let len = fopen.metadata().context(OpenFileSnafu { path })?.len();
-            let bufr = std::io::BufReader::new(fopen);
-            let mut fpart = Part::reader_with_length(bufr, len);
-            if let Some(fname) = path.file_name() {
-                let f: String = fname.to_string_lossy().into();
-                fpart = fpart.file_name(f);
-            }
-            form = form.part("file", fpart);
+        // The form is created for each attempt, because it streams the
+        // files and cannot be cloned. The upload is not idempotent:
+        // duplicates are only detected when the job is processed, so a
+        // request that timed out may still have been accepted.
+        self.retry
+            .run(false, || {
+                let form = upload_form(&meta_json, files)?;
+                file_auth
+                    .apply(self, self.client.post(&url))?
+                    .multipart(form)
+                    .send()
+                    .context(HttpSnafu { url: &url })
+            })?
+            .error_for_status()
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Lists all installed addons of the current collective.
+    ///
+    /// Returns an error if addons are not available on the server.
+    pub fn list_addons(&self, token: &Option<String>) -> Result<AddonList, Error> {
+        let url = &format!("{}/api/v1/sec/addon/archive", self.base_url);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        let resp = self
+            .client
+            .get(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
+            .context(HttpSnafu { url })?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            Err(Error::AddonsUnavailable { url: url.clone() })
+        } else {
+            resp.error_for_status()
+                .context(HttpSnafu { url })?
+                .json::<AddonList>()
+                .context(SerializeRespSnafu)
         }
+    }
 
-        file_auth
-            .apply(self, self.client.post(&url))?
-            .multipart(form)
-            .send()
+    /// Finds the single addon whose name equals or whose id starts
+    /// with the given value.
+    pub fn find_addon(&self, token: &Option<String>, id: &str) -> Result<Addon, Error> {
+        let addons = self.list_addons(token)?;
+        find_by_id_or_name(
+            addons.items,
+            id,
+            |a| (&a.id, &a.name),
+            Error::AddonNotFound { id: id.into() },
+            Error::AddonNotUnique { id: id.into() },
+        )
+    }
+
+    /// Finds the single addon run configuration whose name equals or
+    /// whose id starts with the given value.
+    pub fn find_addon_run_config(
+        &self,
+        token: &Option<String>,
+        id: &str,
+    ) -> Result<AddonRunConfig, Error> {
+        let configs = self.list_addon_run_configs(token)?;
+        find_by_id_or_name(
+            configs.items,
+            id,
+            |c| (&c.id, &c.name),
+            Error::RunConfigNotFound { id: id.into() },
+            Error::RunConfigNotUnique { id: id.into() },
+        )
+    }
+
+    /// Installs the addon at the given url. If `sync` is true, the
+    /// request waits for the installation to complete. Otherwise it is
+    /// done in the background.
+    pub fn install_addon(
+        &self,
+        token: &Option<String>,
+        req: &AddonRegister,
+        sync: bool,
+    ) -> Result<BasicResult, Error> {
+        let url = &format!("{}/api/v1/sec/addon/archive", self.base_url);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        let result = self
+            .client
+            .post(url)
+            .header(DOCSPELL_AUTH, token)
+            .query(&[("sync", sync)])
+            .json(req)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)?;
+        Self::check_addon_install(result)
+    }
+
+    /// Updates the given addon by installing it again from its url.
+    /// The addon can be given by its name or (a prefix of) its id.
+    pub fn update_addon(
+        &self,
+        token: &Option<String>,
+        id: &str,
+        sync: bool,
+    ) -> Result<BasicResult, Error> {
+        let addon_id = self.find_addon(token, id)?.id;
+        let url = &format!("{}/api/v1/sec/addon/archive/{}", self.base_url, addon_id);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        let result = self
+            .client
+            .put(url)
+            .header(DOCSPELL_AUTH, token)
+            .query(&[("sync", sync)])
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)?;
+        Self::check_addon_install(result)
+    }
+
+    /// Deletes the given addon. The addon can be given by its name or
+    /// (a prefix of) its id.
+    pub fn delete_addon(&self, token: &Option<String>, id: &str) -> Result<BasicResult, Error> {
+        let addon_id = self.find_addon(token, id)?.id;
+        let url = &format!("{}/api/v1/sec/addon/archive/{}", self.base_url, addon_id);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .delete(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
             .context(SerializeRespSnafu)
     }
 
+    /// Lists all addon run configurations of the current collective.
+    ///
+    /// Returns an error if addons are not available on the server.
+    pub fn list_addon_run_configs(
+        &self,
+        token: &Option<String>,
+    ) -> Result<AddonRunConfigList, Error> {
+        let url = &format!("{}/api/v1/sec/addon/run-config", self.base_url);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        let resp = self
+            .client
+            .get(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
+            .context(HttpSnafu { url })?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            Err(Error::AddonsUnavailable { url: url.clone() })
+        } else {
+            resp.error_for_status()
+                .context(HttpSnafu { url })?
+                .json::<AddonRunConfigList>()
+                .context(SerializeRespSnafu)
+        }
+    }
+
+    /// Creates a new addon run configuration.
+    pub fn add_addon_run_config(
+        &self,
+        token: &Option<String>,
+        cfg: &AddonRunConfig,
+    ) -> Result<IdResult, Error> {
+        let url = &format!("{}/api/v1/sec/addon/run-config", self.base_url);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .post(url)
+            .header(DOCSPELL_AUTH, token)
+            .json(cfg)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<IdResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Replaces the addon run configuration with the given id. The id
+    /// must be complete, use `find_addon_run_config` to look it up.
+    pub fn update_addon_run_config(
+        &self,
+        token: &Option<String>,
+        id: &str,
+        cfg: &AddonRunConfig,
+    ) -> Result<BasicResult, Error> {
+        let url = &format!("{}/api/v1/sec/addon/run-config/{}", self.base_url, id);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .put(url)
+            .header(DOCSPELL_AUTH, token)
+            .json(cfg)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Deletes the addon run configuration with the given id. The id
+    /// must be complete, use `find_addon_run_config` to look it up.
+    pub fn delete_addon_run_config(
+        &self,
+        token: &Option<String>,
+        id: &str,
+    ) -> Result<BasicResult, Error> {
+        let url = &format!("{}/api/v1/sec/addon/run-config/{}", self.base_url, id);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .delete(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Runs the given addon run configurations on an existing item.
+    /// The item id may be given as a prefix; the run configurations
+    /// by their name or (a prefix of) their id.
+    pub fn run_addons<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        item_id: S,
+        run_configs: &[String],
+    ) -> Result<BasicResult, Error> {
+        let item_id = self.require_item_id(token, item_id, SearchMode::All)?;
+        let addon_run_config_ids = run_configs
+            .iter()
+            .map(|c| self.find_addon_run_config(token, c).map(|rc| rc.id))
+            .collect::<Result<Vec<String>, Error>>()?;
+        let req = AddonRunExistingItem {
+            item_id,
+            additional_items: vec![],
+            addon_run_config_ids,
+        };
+        let url = &format!("{}/api/v1/sec/addon/run/existingitem", self.base_url);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .post(url)
+            .header(DOCSPELL_AUTH, token)
+            .json(&req)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Gets the settings of the current collective.
+    pub fn get_collective_settings(
+        &self,
+        token: &Option<String>,
+    ) -> Result<CollectiveSettings, Error> {
+        let url = &format!("{}/api/v1/sec/collective/settings", self.base_url);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .get(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<CollectiveSettings>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Submits a task that learns the classifier of the current
+    /// collective from its items.
+    pub fn start_learn_classifier(&self, token: &Option<String>) -> Result<BasicResult, Error> {
+        let url = &format!(
+            "{}/api/v1/sec/collective/classifier/startLearning",
+            self.base_url
+        );
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .post(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Gets the client settings for the given client id. Depending on
+    /// `scope`, the settings of the user, the collective or both
+    /// merged are returned.
+    pub fn get_client_settings(
+        &self,
+        token: &Option<String>,
+        client_id: &str,
+        scope: ClientSettingsScope,
+    ) -> Result<ClientSettings, Error> {
+        let url = &self.client_settings_url(client_id, scope);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .get(url)
+            .header(DOCSPELL_AUTH, token)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<ClientSettings>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Replaces the client settings of the user or collective for the
+    /// given client id. The merged view cannot be written to.
+    pub fn set_client_settings(
+        &self,
+        token: &Option<String>,
+        client_id: &str,
+        scope: ClientSettingsScope,
+        settings: &ClientSettings,
+    ) -> Result<BasicResult, Error> {
+        let url = &self.client_settings_url(client_id, scope);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .put(url)
+            .header(DOCSPELL_AUTH, token)
+            .json(settings)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    fn client_settings_url(&self, client_id: &str, scope: ClientSettingsScope) -> String {
+        match scope {
+            ClientSettingsScope::Merged => {
+                format!("{}/api/v1/sec/clientSettings/{}", self.base_url, client_id)
+            }
+            _ => format!(
+                "{}/api/v1/sec/clientSettings/{}/{}",
+                self.base_url,
+                scope.as_str(),
+                client_id
+            ),
+        }
+    }
+
     /// Submits a task on the Docspell server, that (re)generates all preview images.
     ///
     /// This is needed if the preview dpi setting has been changed.
@@ -720,7 +1299,7 @@ impl Client {
             .post(url)
             .header(DOCSPELL_ADMIN, admin_secret.into())
             .header(reqwest::header::CONTENT_LENGTH, 0)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -739,7 +1318,7 @@ impl Client {
             .post(url)
             .header(DOCSPELL_ADMIN, admin_secret.into())
             .header(reqwest::header::CONTENT_LENGTH, 0)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -760,7 +1339,7 @@ impl Client {
             .post(url)
             .header(DOCSPELL_ADMIN, admin_secret.into())
             .header(reqwest::header::CONTENT_LENGTH, 0)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -780,7 +1359,7 @@ impl Client {
             .post(url)
             .header(DOCSPELL_ADMIN, admin_secret.into())
             .json(&account)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<ResetPasswordResp>()
@@ -797,7 +1376,7 @@ impl Client {
             .post(url)
             .header(DOCSPELL_ADMIN, admin_secret.into())
             .json(&account)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -814,7 +1393,7 @@ impl Client {
             .post(url)
             .header(DOCSPELL_ADMIN, admin_secret.into())
             .json(&req)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -831,7 +1410,7 @@ impl Client {
             .post(url)
             .header(DOCSPELL_ADMIN, admin_secret.into())
             .json(&req)
-            .send()
+            .send_retry(&self.retry)
             .and_then(|r| r.error_for_status())
             .context(HttpSnafu { url })?
             .json::<BasicResult>()
@@ -840,6 +1419,89 @@ impl Client {
 
     // --- Helpers
 
+    /// Sends the given body via `PUT` to a sub resource of an item,
+    /// like `/item/{id}/corrOrg`.
+    fn update_item<S: AsRef<str>, B: Serialize + ?Sized>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        segment: &str,
+        body: &B,
+    ) -> Result<BasicResult, Error> {
+        let item_id = self.require_item_id(token, id, SearchMode::All)?;
+        let url = &format!("{}/api/v1/sec/item/{}/{}", self.base_url, item_id, segment);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .put(url)
+            .header(DOCSPELL_AUTH, token)
+            .json(body)
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    /// Searches the entities at `/api/v1/sec/{segment}` for the given
+    /// name and returns the one with exactly this name.
+    fn find_by_name(
+        &self,
+        token: &Option<String>,
+        segment: &str,
+        name: &str,
+    ) -> Result<Option<IdName>, Error> {
+        let url = &format!("{}/api/v1/sec/{}", self.base_url, segment);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        let list = self
+            .client
+            .get(url)
+            .header(DOCSPELL_AUTH, token)
+            .query(&[("q", name)])
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<IdNameList>()
+            .context(SerializeRespSnafu)?;
+        Ok(list.items.into_iter().find(|e| e.name == name))
+    }
+
+    /// Completes all given item ids and posts them to the `itemlink`
+    /// endpoint given by `action`.
+    fn post_item_links<S: AsRef<str>>(
+        &self,
+        token: &Option<String>,
+        id: S,
+        related: &[String],
+        action: &str,
+    ) -> Result<BasicResult, Error> {
+        let item = self.require_item_id(token, id, SearchMode::All)?;
+        let related = related
+            .iter()
+            .map(|r| self.require_item_id(token, r, SearchMode::All))
+            .collect::<Result<Vec<String>, Error>>()?;
+        let url = &format!("{}/api/v1/sec/itemlink/{}", self.base_url, action);
+        let token = session::session_token(token, self).context(SessionSnafu)?;
+        self.client
+            .post(url)
+            .header(DOCSPELL_AUTH, token)
+            .json(&ItemLinkData { item, related })
+            .send_retry(&self.retry)
+            .and_then(|r| r.error_for_status())
+            .context(HttpSnafu { url })?
+            .json::<BasicResult>()
+            .context(SerializeRespSnafu)
+    }
+
+    fn check_addon_install(result: BasicResult) -> Result<BasicResult, Error> {
+        if result.success {
+            Ok(result)
+        } else {
+            Err(Error::AddonInstall {
+                message: result.message,
+            })
+        }
+    }
+
     fn require_item_id<S: AsRef<str>>(
         &self,
         token: &Option<String>,
@@ -893,6 +1555,81 @@ impl Client {
     }
 }
 
+impl Transient for Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            Error::Http { source, .. } => source.is_transient(),
+            _ => false,
+        }
+    }
+
+    fn is_connect(&self) -> bool {
+        match self {
+            Error::Http { source, .. } => source.is_connect(),
+            _ => false,
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            Error::Http { source, .. } => Transient::status(source),
+            Error::UnexpectedStatus { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the single element whose name equals `id` or, if there is
+/// none, whose id starts with it. `key` returns the id and name of an
+/// element.
+fn find_by_id_or_name<T, F>(
+    items: Vec<T>,
+    id: &str,
+    key: F,
+    not_found: Error,
+    not_unique: Error,
+) -> Result<T, Error>
+where
+    F: Fn(&T) -> (&String, &String),
+{
+    let mut found: Vec<T> = Vec::new();
+    for item in items {
+        let (item_id, name) = key(&item);
+        if name == id {
+            return Ok(item);
+        }
+        if item_id.starts_with(id) {
+            found.push(item);
+        }
+    }
+    match found.len() {
+        0 => Err(not_found),
+        1 => Ok(found.remove(0)),
+        _ => Err(not_unique),
+    }
+}
+
+fn upload_form(meta_json: &[u8], files: &[&Path]) -> Result<Form, Error> {
+    let meta_part = Part::bytes(meta_json.to_vec())
+        .mime_str(APP_JSON)
+        .context(MimeSnafu { raw: APP_JSON })?;
+    let mut form = Form::new().part("meta", meta_part);
+    for path in files {
+        log::debug!("Adding to request: {}", path.display());
+
+        let fopen = File::open(path).context(OpenFileSnafu { path })?;
+        let len = fopen.metadata().context(OpenFileSnafu { path })?.len();
+        let bufr = std::io::BufReader::new(fopen);
+        let mut fpart = Part::reader_with_length(bufr, len);
+        if let Some(fname) = path.file_name() {
+            let f: String = fname.to_string_lossy().into();
+            fpart = fpart.file_name(f);
+        }
+        form = form.part("file", fpart);
+    }
+    Ok(form)
+}
+
 /// Defines methods to authenticate when uploading files.
 ///
 /// Either use a [source
@@ -985,9 +1722,9 @@ impl Download {
     }
 
     /// Copies the bytes from the response into the give writer.
-    pub fn copy_to<W: ?Sized>(&mut self, w: &mut W) -> Result<u64, Error>
+    pub fn copy_to<W>(&mut self, w: &mut W) -> Result<u64, Error>
     where
-        W: Write,
+        W: Write + ?Sized,
     {
         let resp = &mut self.resp;
         resp.copy_to(w).context(HttpSnafu {
@@ -1064,7 +1801,7 @@ impl DownloadRef {
             .client
             .get(url)
             .header(DOCSPELL_AUTH, &token)
-            .send()
+            .send_retry(&client.retry)
             .context(HttpSnafu { url })?;
         if resp.status() == StatusCode::NOT_FOUND {
             Ok(None)
@@ -1084,7 +1821,7 @@ impl DownloadRef {
             .client
             .head(url)
             .header(DOCSPELL_AUTH, &token)
-            .send()
+            .send_retry(&client.retry)
             .context(HttpSnafu { url })?;
         if resp.status() == StatusCode::NOT_FOUND {
             Ok(true)
@@ -55,6 +55,171 @@ pub struct ItemDetail {
     pub archives: Vec<Attachment>,
     pub tags: Vec<Tag>,
     pub customfields: Vec<CustomField>,
+    #[serde(default)]
+    pub related: Vec<IdName>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemProposals {
+    #[serde(alias = "corrOrg")]
+    pub corr_org: Vec<IdName>,
+    #[serde(alias = "corrPerson")]
+    pub corr_person: Vec<IdName>,
+    #[serde(alias = "concPerson")]
+    pub conc_person: Vec<IdName>,
+    #[serde(alias = "concEquipment")]
+    pub conc_equip: Vec<IdName>,
+    #[serde(alias = "itemDate")]
+    pub item_date: Vec<i64>,
+    #[serde(alias = "dueDate")]
+    pub due_date: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemProposalsEntry {
+    pub id: String,
+    pub name: String,
+    pub proposals: ItemProposals,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemProposalsList {
+    pub items: Vec<ItemProposalsEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemLinkData {
+    pub item: String,
+    pub related: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OptionalId {
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OptionalDate {
+    pub date: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OptionalText {
+    pub text: Option<String>,
+}
+
+/// A list of entities, like folders or organizations, where only id
+/// and name are of interest.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdNameList {
+    pub items: Vec<IdName>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Addon {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub created: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddonList {
+    pub items: Vec<Addon>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddonRegister {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AddonRef {
+    #[serde(alias = "addonId", rename(serialize = "addonId"))]
+    pub addon_id: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub args: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AddonRunConfig {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    #[serde(alias = "userId", rename(serialize = "userId"))]
+    pub user_id: Option<String>,
+    pub schedule: Option<String>,
+    pub trigger: Vec<String>,
+    pub addons: Vec<AddonRef>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddonRunConfigList {
+    pub items: Vec<AddonRunConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddonRunExistingItem {
+    #[serde(alias = "itemId", rename(serialize = "itemId"))]
+    pub item_id: String,
+    #[serde(alias = "additionalItems", rename(serialize = "additionalItems"))]
+    pub additional_items: Vec<String>,
+    #[serde(alias = "addonRunConfigIds", rename(serialize = "addonRunConfigIds"))]
+    pub addon_run_config_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectiveSettings {
+    pub language: String,
+    #[serde(alias = "integrationEnabled")]
+    pub integration_enabled: bool,
+    pub classifier: ClassifierSetting,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassifierSetting {
+    pub schedule: String,
+    #[serde(alias = "itemCount")]
+    pub item_count: i32,
+    #[serde(alias = "categoryList", default)]
+    pub category_list: Vec<String>,
+    #[serde(alias = "listType")]
+    pub list_type: String,
+}
+
+/// UI settings of a Docspell client, stored as arbitrary JSON.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(transparent)]
+pub struct ClientSettings(pub serde_json::Value);
+
+/// Where client settings are stored. `Merged` combines the user's
+/// settings with those of the collective and is read-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientSettingsScope {
+    User,
+    Collective,
+    Merged,
+}
+
+impl ClientSettingsScope {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ClientSettingsScope::User => "user",
+            ClientSettingsScope::Collective => "collective",
+            ClientSettingsScope::Merged => "merged",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdResult {
+    pub success: bool,
+    pub message: String,
+    pub id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -408,6 +573,17 @@ pub struct Item {
     pub customfields: Vec<CustomField>,
     pub notes: Option<String>,
     pub highlighting: Vec<Highlight>,
+    #[serde(default)]
+    pub related: Vec<IdName>,
+}
+
+impl Item {
+    pub fn to_idname(&self) -> IdName {
+        IdName {
+            id: self.id.clone(),
+            name: self.name.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
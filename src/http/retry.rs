@@ -0,0 +1,231 @@
+//! Retrying requests that failed due to transient errors.
+//!
+//! A [`RetryPolicy`] defines how often and with which delay a request
+//! is repeated. Requests are retried if the connection could not be
+//! established, timed out or if the server responded with one of the
+//! configured status codes (like `503 Service Unavailable`).
+//!
+//! Requests that are not idempotent, like `POST`, may have been
+//! processed by the server even if they time out or get such a
+//! response. They are only retried if the connection could not be
+//! established.
+//!
+//! The delay between attempts is doubled after each attempt, starting
+//! with the configured backoff.
+
+use std::time::Duration;
+
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::Method;
+
+/// The maximum delay between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Defines when and how often a request is retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one. A
+    /// value of 1 (or 0) disables retries.
+    pub max_attempts: u32,
+
+    /// The delay before the first retry. It is doubled for every
+    /// following one.
+    pub backoff: Duration,
+
+    /// Response status codes that cause a retry.
+    pub status_codes: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff: Duration::from_millis(500),
+            status_codes: vec![429, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay to wait before the given attempt, where the
+    /// first retry is attempt 2.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(2));
+        self.backoff.saturating_mul(factor).min(MAX_BACKOFF)
+    }
+
+    /// Whether a response with the given status should be retried.
+    pub fn is_retry_status(&self, status: u16) -> bool {
+        self.status_codes.contains(&status)
+    }
+
+    /// Whether trying again later may succeed after all attempts
+    /// failed with the given error. This is the case for transient
+    /// errors and for status errors with a retryable status or any
+    /// server error status, like from a server that is down behind a
+    /// proxy.
+    pub fn is_transient<E: Transient>(&self, err: &E) -> bool {
+        err.is_transient()
+            || err
+                .status()
+                .is_some_and(|s| self.is_retry_status(s) || (500..600).contains(&s))
+    }
+
+    /// Runs `send` until it succeeds with a response whose status is
+    /// not retryable, it fails with a non transient error or the
+    /// maximum number of attempts is reached. The last result is
+    /// returned. If the request is not `idempotent`, it is only
+    /// retried if the connection could not be established.
+    pub fn run<E, F>(&self, idempotent: bool, mut send: F) -> Result<Response, E>
+    where
+        E: Transient + std::fmt::Display,
+        F: FnMut() -> Result<Response, E>,
+    {
+        let mut attempt = 1;
+        loop {
+            let result = send();
+            if attempt >= self.max_attempts {
+                return result;
+            }
+            match &result {
+                Ok(resp) if idempotent && self.is_retry_status(resp.status().as_u16()) => {
+                    log::warn!(
+                        "Got status {} from {} (attempt {}/{})",
+                        resp.status(),
+                        resp.url(),
+                        attempt,
+                        self.max_attempts
+                    );
+                }
+                Err(err) if err.is_transient() && (idempotent || err.is_connect()) => {
+                    log::warn!(
+                        "Request failed (attempt {}/{}): {}",
+                        attempt,
+                        self.max_attempts,
+                        err
+                    );
+                }
+                _ => return result,
+            }
+            attempt += 1;
+            let delay = self.delay(attempt);
+            log::info!("Retrying in {:?}", delay);
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+/// Errors that may go away when trying again.
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+
+    /// Whether the connection could not be established, so the
+    /// request has not been sent.
+    fn is_connect(&self) -> bool;
+
+    /// The response status, if this is a status error.
+    fn status(&self) -> Option<u16>;
+}
+
+impl Transient for reqwest::Error {
+    fn is_transient(&self) -> bool {
+        self.is_connect() || self.is_timeout()
+    }
+
+    fn is_connect(&self) -> bool {
+        reqwest::Error::is_connect(self)
+    }
+
+    fn status(&self) -> Option<u16> {
+        reqwest::Error::status(self).map(|s| s.as_u16())
+    }
+}
+
+/// Whether sending a request with this method multiple times has the
+/// same effect as sending it once.
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Sends a request applying a [`RetryPolicy`].
+pub trait SendRetry {
+    fn send_retry(self, policy: &RetryPolicy) -> reqwest::Result<Response>;
+}
+
+impl SendRetry for RequestBuilder {
+    /// Sends the request, retrying it as defined by the policy. If the
+    /// request cannot be cloned (for example when the body is a
+    /// stream), it is sent only once.
+    fn send_retry(self, policy: &RetryPolicy) -> reqwest::Result<Response> {
+        match self.try_clone().map(|b| b.build()) {
+            Some(Ok(req)) => {
+                let idempotent = is_idempotent(req.method());
+                policy.run(idempotent, || {
+                    self.try_clone().expect("Request is clonable").send()
+                })
+            }
+            _ => self.send(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_retry_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            backoff: Duration::from_millis(100),
+            status_codes: vec![],
+        };
+        assert_eq!(policy.delay(2), Duration::from_millis(100));
+        assert_eq!(policy.delay(3), Duration::from_millis(200));
+        assert_eq!(policy.delay(5), Duration::from_millis(800));
+        assert_eq!(policy.delay(40), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn unit_retry_status() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retry_status(503));
+        assert!(!policy.is_retry_status(500));
+        assert!(!policy.is_retry_status(404));
+        assert!(is_idempotent(&Method::PUT));
+        assert!(!is_idempotent(&Method::POST));
+    }
+
+    struct StatusError(u16);
+
+    impl Transient for StatusError {
+        fn is_transient(&self) -> bool {
+            false
+        }
+
+        fn is_connect(&self) -> bool {
+            false
+        }
+
+        fn status(&self) -> Option<u16> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn unit_transient_status_error() {
+        let policy = RetryPolicy {
+            status_codes: vec![429, 408],
+            ..RetryPolicy::default()
+        };
+        assert!(policy.is_transient(&StatusError(429)));
+        assert!(policy.is_transient(&StatusError(408)));
+        assert!(policy.is_transient(&StatusError(500)));
+        assert!(policy.is_transient(&StatusError(503)));
+        assert!(!policy.is_transient(&StatusError(400)));
+        assert!(!policy.is_transient(&StatusError(404)));
+    }
+}
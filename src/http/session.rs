@@ -194,7 +194,7 @@ fn read_token_file(path: &Path) -> Result<AuthResp, Error> {
     if path.exists() {
         acquire_lock(path, false)?;
 
-        let cnt = std::fs::read_to_string(&path).context(ReadSessionFileSnafu { path })?;
+        let cnt = std::fs::read_to_string(path).context(ReadSessionFileSnafu { path })?;
         let resp: AuthResp = serde_json::from_str(&cnt).context(SerializeSessionSnafu)?;
         Ok(resp)
     } else {
@@ -19,12 +19,12 @@ pub fn digest<D: Digest + Default, R: io::Read>(reader: &mut R) -> Result<String
     loop {
         let n = match reader.read(&mut buffer) {
             Ok(n) => n,
-            Err(_) => return Err(io::Error::new(io::ErrorKind::Other, "Could not read file")),
+            Err(_) => return Err(io::Error::other("Could not read file")),
         };
         sh.update(&buffer[..n]);
         if n == 0 || n < BUFFER_SIZE {
             break;
         }
     }
-    Ok(hex::encode(&sh.finalize()))
+    Ok(hex::encode(sh.finalize()))
 }
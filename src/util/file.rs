@@ -1,11 +1,12 @@
+use std::fmt::Display;
 use std::path::{Path, PathBuf};
 
-use crate::cli::opts::FileAction;
+use crate::cli::opts::{ActionSpec, FileAction};
 use snafu::{ResultExt, Snafu};
 use std::io;
 
 /// Puts `suffix` in the filename before the extension.
-pub fn splice_name(fname: &str, suffix: &i32) -> String {
+pub fn splice_name<S: Display + ?Sized>(fname: &str, suffix: &S) -> String {
     let p = PathBuf::from(fname);
 
     match p.extension() {
@@ -18,7 +19,25 @@ pub fn splice_name(fname: &str, suffix: &i32) -> String {
     }
 }
 
-fn delete_parent_if_empty(file: &Path, root: Option<&PathBuf>) -> Result<(), std::io::Error> {
+/// Returns the path, if no file exists there. Otherwise a counter is
+/// put in the file name, like `scan_1.pdf`, until the path is free.
+pub fn unique_path(path: PathBuf) -> PathBuf {
+    let name = match path.file_name().and_then(|n| n.to_str()) {
+        Some(n) => n.to_string(),
+        None => return path,
+    };
+    let mut candidate = path.clone();
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = path.with_file_name(splice_name(&name, &counter));
+        counter += 1;
+    }
+    candidate
+}
+
+/// Deletes the directory of the file, if it is empty and below the
+/// given root.
+pub fn delete_parent_if_empty(file: &Path, root: Option<&PathBuf>) -> Result<(), std::io::Error> {
     match (root, file.parent()) {
         (Some(r), Some(p)) => {
             if p != r && std::fs::read_dir(p)?.next().is_none() {
@@ -102,33 +121,109 @@ pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Resu
 pub enum FileActionResult {
     Deleted(PathBuf),
     Moved(PathBuf),
+    Copied(PathBuf),
     Nothing,
 }
 
 impl FileAction {
+    /// Returns the action for uploaded files.
+    pub fn spec(&self) -> ActionSpec {
+        match (&self.move_to, &self.copy_to) {
+            (Some(dir), _) => ActionSpec::Move(dir.clone()),
+            (_, Some(dir)) => ActionSpec::Copy(dir.clone()),
+            _ if self.delete => ActionSpec::Delete,
+            _ => ActionSpec::Keep,
+        }
+    }
+
+    /// Returns the action for files that are already in Docspell.
+    pub fn duplicate_spec(&self) -> ActionSpec {
+        self.on_duplicate.clone().unwrap_or_else(|| self.spec())
+    }
+
+    /// Returns whether the id of the item is needed to apply the
+    /// action to an uploaded file.
+    pub fn needs_item_id(&self) -> bool {
+        self.rename_with_id && matches!(self.spec(), ActionSpec::Move(_) | ActionSpec::Copy(_))
+    }
+
+    /// Applies the action for uploaded files.
     pub fn execute(
         &self,
         file: &Path,
         root: Option<&PathBuf>,
+        item_id: Option<&str>,
     ) -> Result<FileActionResult, std::io::Error> {
-        match &self.move_to {
-            Some(target) => Self::move_file(file, root, target).map(FileActionResult::Moved),
-            None => {
-                if self.delete {
-                    Self::delete_file(file, root)
-                        .map(|_| FileActionResult::Deleted(file.to_path_buf()))
-                } else {
-                    Ok(FileActionResult::Nothing)
+        self.apply(&self.spec(), file, root, item_id)
+    }
+
+    /// Applies the action for files that are already in Docspell.
+    pub fn execute_duplicate(
+        &self,
+        file: &Path,
+        root: Option<&PathBuf>,
+        item_id: Option<&str>,
+    ) -> Result<FileActionResult, std::io::Error> {
+        self.apply(&self.duplicate_spec(), file, root, item_id)
+    }
+
+    /// Applies the given action. When moving or copying, the options
+    /// `--date-dir` and `--rename-with-id` are used to determine the
+    /// target file.
+    pub fn apply(
+        &self,
+        spec: &ActionSpec,
+        file: &Path,
+        root: Option<&PathBuf>,
+        item_id: Option<&str>,
+    ) -> Result<FileActionResult, std::io::Error> {
+        match spec {
+            ActionSpec::Keep => Ok(FileActionResult::Nothing),
+            ActionSpec::Delete => {
+                Self::delete_file(file, root).map(|_| FileActionResult::Deleted(file.to_path_buf()))
+            }
+            ActionSpec::Move(dir) => {
+                let target = self.target_dir(dir);
+                Self::move_file(file, root, &target, self.id_suffix(item_id))
+                    .map(FileActionResult::Moved)
+            }
+            ActionSpec::Copy(dir) => {
+                let target_file =
+                    Self::target_file(file, root, &self.target_dir(dir), self.id_suffix(item_id));
+                log::debug!(
+                    "Copy file '{}' -> '{}'",
+                    file.display(),
+                    target_file.display()
+                );
+                if let Some(parent) = target_file.parent() {
+                    std::fs::create_dir_all(parent)?;
                 }
+                std::fs::copy(file, &target_file)?;
+                Ok(FileActionResult::Copied(target_file))
             }
         }
     }
 
-    fn move_file(
+    fn target_dir(&self, dir: &Path) -> PathBuf {
+        match &self.date_dir {
+            Some(format) => dir.join(chrono::Local::now().format(format).to_string()),
+            None => dir.to_path_buf(),
+        }
+    }
+
+    fn id_suffix<'a>(&self, item_id: Option<&'a str>) -> Option<&'a str> {
+        item_id.filter(|_| self.rename_with_id)
+    }
+
+    /// Returns the path of the file below `target`. The directory
+    /// structure below `root` is retained. If a file with this name
+    /// already exists, a counter is added to the name.
+    fn target_file(
         file: &Path,
         root: Option<&PathBuf>,
         target: &Path,
-    ) -> Result<PathBuf, std::io::Error> {
+        suffix: Option<&str>,
+    ) -> PathBuf {
         let target_file = match root {
             Some(r) => {
                 let part = file.strip_prefix(r).unwrap();
@@ -136,6 +231,20 @@ impl FileAction {
             }
             None => target.join(file.file_name().unwrap()),
         };
+        let target_file = match (suffix, target_file.file_name().and_then(|n| n.to_str())) {
+            (Some(id), Some(name)) => target_file.with_file_name(splice_name(name, id)),
+            _ => target_file,
+        };
+        unique_path(target_file)
+    }
+
+    fn move_file(
+        file: &Path,
+        root: Option<&PathBuf>,
+        target: &Path,
+        suffix: Option<&str>,
+    ) -> Result<PathBuf, std::io::Error> {
+        let target_file = Self::target_file(file, root, target, suffix);
         log::debug!(
             "Move file '{}' -> '{}'",
             file.display(),
@@ -169,5 +278,56 @@ mod tests {
         assert_eq!(splice_name("abc.pdf", &1), "abc_1.pdf");
         assert_eq!(splice_name("abc", &1), "abc_1");
         assert_eq!(splice_name("stuff.tar.gz", &2), "stuff.tar_2.gz");
+        assert_eq!(splice_name("scan.pdf", "abc"), "scan_abc.pdf");
+    }
+
+    #[test]
+    fn unit_file_action_copy() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let root = dir.join("in");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        let file = root.join("sub").join("scan.pdf");
+        std::fs::write(&file, "pdf").unwrap();
+
+        let action = FileAction {
+            delete: true,
+            move_to: None,
+            copy_to: None,
+            date_dir: None,
+            rename_with_id: true,
+            process_timeout: 300,
+            on_duplicate: Some("copy:/tmp/dupes".parse().unwrap()),
+        };
+        assert_eq!(action.spec(), ActionSpec::Delete);
+        assert_eq!(
+            action.duplicate_spec(),
+            ActionSpec::Copy(PathBuf::from("/tmp/dupes"))
+        );
+        assert!(!action.needs_item_id());
+        assert!("move:".parse::<ActionSpec>().is_err());
+
+        let spec = ActionSpec::Copy(dir.join("out"));
+        let res = action
+            .apply(&spec, &file, Some(&root), Some("id1"))
+            .unwrap();
+        let target = dir.join("out").join("sub").join("scan_id1.pdf");
+        assert!(matches!(res, FileActionResult::Copied(p) if p == target));
+        assert!(file.exists() && target.exists());
+
+        // an existing file is not overwritten
+        let res = action
+            .apply(&spec, &file, Some(&root), Some("id1"))
+            .unwrap();
+        let second = dir.join("out").join("sub").join("scan_id1_1.pdf");
+        assert!(matches!(res, FileActionResult::Copied(p) if p == second));
+
+        let spec = ActionSpec::Move(dir.join("out"));
+        let res = action
+            .apply(&spec, &file, Some(&root), Some("id1"))
+            .unwrap();
+        let third = dir.join("out").join("sub").join("scan_id1_2.pdf");
+        assert!(matches!(res, FileActionResult::Moved(p) if p == third));
+        assert!(target.exists() && second.exists() && !file.exists());
     }
 }
@@ -19,7 +19,7 @@ pub fn pass_key(entry: &str, key: &str) -> Result<String, io::Error> {
         .ok_or_else(|| io_err(&format!("No line found for key: {:?}", key)))?;
 
     let len = key.len() + 1;
-    Ok((&line[len..]).trim().into())
+    Ok(line[len..].trim().into())
 }
 
 fn pass_exec(entry: &str) -> Result<String, io::Error> {
@@ -39,5 +39,5 @@ fn pass_exec(entry: &str) -> Result<String, io::Error> {
 }
 
 fn io_err(msg: &str) -> io::Error {
-    io::Error::new(io::ErrorKind::Other, msg)
+    io::Error::other(msg)
 }
@@ -3,6 +3,7 @@ use assert_cmd::prelude::*;
 use std::{io, process::Command};
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum Error {
     Cargo(CargoError),
     IO(io::Error),
@@ -2,8 +2,20 @@ mod common;
 
 use crate::common::{mk_cmd, Result};
 use assert_cmd::prelude::*;
-use dsc::http::payload::{BasicResult, ItemDetail, SearchResult, SourceAndTags, Summary};
+use dsc::http::payload::{
+    BasicResult, ClassifierSetting, ClientSettings, ItemDetail, ItemProposals, SearchResult,
+    SourceAndTags, Summary,
+};
 use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 use std::{io::Write, path::Path, process::Command};
 
 const ITEM_ID1: &str = "2wKtSUVt3Kj-mAmexmm1jFe-BU6aY6PN4vo-5cpaDD2EyRm";
@@ -42,7 +54,7 @@ fn remote_register() -> Result<()> {
     let mut cmd = mk_cmd()?;
     let out = cmd
         .arg("register")
-        .args(&["-c", "demo2", "-l", "demo2", "--password", "test"])
+        .args(["-c", "demo2", "-l", "demo2", "--password", "test"])
         .output()?;
 
     let out: BasicResult = serde_json::from_slice(out.stdout.as_slice())?;
@@ -64,7 +76,7 @@ fn remote_upload_web() -> Result<()> {
 fn remote_upload_single_delete() -> Result<()> {
     let testname = "hello.txt";
     let testpath = Path::new(testname);
-    let mut testfile = std::fs::File::create(&testpath)?;
+    let mut testfile = std::fs::File::create(testpath)?;
     writeln!(&mut testfile, "hello world!")?;
 
     assert!(testpath.exists());
@@ -84,7 +96,7 @@ fn remote_upload_int_endpoint() -> Result<()> {
     let mut cmd = mk_cmd()?;
     let assert = cmd
         .arg("upload")
-        .args(&[
+        .args([
             "-c",
             "demo",
             "-i",
@@ -113,7 +125,7 @@ fn remote_upload_int_endpoint_guess_collective() -> Result<()> {
     let mut cmd = mk_cmd()?;
     let assert = cmd
         .arg("upload")
-        .args(&[
+        .args([
             "-i",
             "--header",
             "Docspell-Integration:test123",
@@ -321,6 +333,69 @@ fn remote_item_get() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn remote_item_proposals() -> Result<()> {
+    let mut cmd = mk_cmd()?;
+    let out = cmd
+        .arg("item")
+        .arg("proposals")
+        .arg("--id")
+        .arg(&ITEM_ID2[0..7])
+        .output()?;
+    let _props: ItemProposals = serde_json::from_slice(out.stdout.as_slice())?;
+    out.assert().success().stderr("");
+    Ok(())
+}
+
+#[test]
+fn remote_item_related() -> Result<()> {
+    let mut cmd = mk_cmd()?;
+    let out = cmd
+        .arg("item")
+        .arg("related")
+        .arg("--id")
+        .arg(&ITEM_ID1[0..7])
+        .output()?;
+    let _related: SearchResult = serde_json::from_slice(out.stdout.as_slice())?;
+    out.assert().success().stderr("");
+    Ok(())
+}
+
+#[test]
+fn remote_classifier_settings() -> Result<()> {
+    let mut cmd = mk_cmd()?;
+    let out = cmd.arg("classifier").arg("settings").output()?;
+    let _settings: ClassifierSetting = serde_json::from_slice(out.stdout.as_slice())?;
+    out.assert().success().stderr("");
+    Ok(())
+}
+
+#[test]
+fn remote_client_settings_copy() -> Result<()> {
+    let mut cmd = mk_cmd()?;
+    let out = cmd
+        .arg("client-settings")
+        .arg("get")
+        .arg("--scope")
+        .arg("user")
+        .output()?;
+    let _settings: ClientSettings = serde_json::from_slice(out.stdout.as_slice())?;
+    out.assert().success().stderr("");
+
+    let mut cmd = mk_cmd()?;
+    let out = cmd
+        .arg("client-settings")
+        .arg("copy")
+        .arg("--from")
+        .arg("user")
+        .arg("--to")
+        .arg("user")
+        .output()?;
+    let res: BasicResult = serde_json::from_slice(out.stdout.as_slice())?;
+    assert!(res.success);
+    Ok(())
+}
+
 #[test]
 fn remote_item_tags_add() -> Result<()> {
     let mut cmd = mk_cmd()?;
@@ -435,3 +510,656 @@ fn remote_item_fields_set_bad_value() -> Result<()> {
     ));
     Ok(())
 }
+
+/// Starts a server that answers uploads to a source with the given
+/// result. Checks for duplicates always report a new file.
+fn stub_upload_server(result: BasicResult) -> Result<(String, mpsc::Receiver<String>)> {
+    let result = serde_json::to_string(&result)?;
+    stub_server(move |request| {
+        if request.starts_with("GET /api/v1/open/checkfile/") {
+            r#"{"exists":false,"items":[],"file":null}"#.to_string()
+        } else {
+            result.clone()
+        }
+    })
+}
+
+/// Starts a server that answers each request with the json returned
+/// by `respond` for the request (head and body). All requests are
+/// sent to the returned channel.
+fn stub_server<F>(respond: F) -> Result<(String, mpsc::Receiver<String>)>
+where
+    F: Fn(&str) -> String + Send + 'static,
+{
+    stub_status_server(move |request| (200, respond(request)))
+}
+
+/// Like `stub_server`, but `respond` returns the status code, too.
+fn stub_status_server<F>(respond: F) -> Result<(String, mpsc::Receiver<String>)>
+where
+    F: Fn(&str) -> (u16, String) + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let mut request = String::new();
+            let mut length = 0;
+            let mut chunked = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                let lower = line.to_lowercase();
+                if let Some(n) = lower.strip_prefix("content-length:") {
+                    length = n.trim().parse().unwrap_or(0);
+                }
+                chunked = chunked || lower.starts_with("transfer-encoding: chunked");
+                request.push_str(&line);
+            }
+            let mut body = Vec::new();
+            if chunked {
+                while !body.ends_with(b"0\r\n\r\n") {
+                    if reader.read_until(b'\n', &mut body).unwrap_or(0) == 0 {
+                        break;
+                    }
+                }
+            } else {
+                body.resize(length, 0);
+                let _ = reader.read_exact(&mut body);
+            }
+            request.push_str(&String::from_utf8_lossy(&body));
+
+            let (status, json) = respond(&request);
+            let _ = write!(
+                &stream,
+                "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                json.len(),
+                json
+            );
+            if tx.send(request).is_err() {
+                break;
+            }
+        }
+    });
+    Ok((url, rx))
+}
+
+#[test]
+fn watch_upload_moved_file() -> Result<()> {
+    let base = Path::new("target/test_watch_moved_file");
+    let _ = fs::remove_dir_all(base);
+    let watched = base.join("inbox");
+    let outside = base.join("outside");
+    fs::create_dir_all(&watched)?;
+    fs::create_dir_all(outside.join("batch"))?;
+    fs::write(outside.join("scan.pdf"), "a scanned file")?;
+    fs::write(outside.join("batch").join("page.pdf"), "another file")?;
+
+    let (url, requests) = stub_upload_server(basic_result(true, "Files submitted."))?;
+    let mut child = mk_cmd()?
+        .args(["-d", &url, "watch", "--recursive", "--source", "src1"])
+        .args(["--delay", "1", "--stable-secs", "0", "--queue-file"])
+        .arg(base.join("queue.json"))
+        .arg(&watched)
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Wait for the watcher to be set up
+    let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+    for line in stderr.by_ref() {
+        if line?.starts_with("Press Ctrl-C") {
+            break;
+        }
+    }
+    thread::spawn(move || stderr.for_each(drop));
+
+    fs::rename(outside.join("scan.pdf"), watched.join("scan.pdf"))?;
+    fs::rename(outside.join("batch"), watched.join("batch"))?;
+
+    let mut received = Vec::new();
+    while received.len() < 2 {
+        match requests.recv_timeout(Duration::from_secs(30)) {
+            Ok(req) if req.starts_with("POST ") => received.push(req),
+            Ok(_) => (),
+            Err(_) => break,
+        }
+    }
+    child.kill()?;
+    child.wait()?;
+    fs::remove_dir_all(base)?;
+
+    assert_eq!(received.len(), 2);
+    assert!(received
+        .iter()
+        .all(|r| r.starts_with("POST /api/v1/open/upload/item/src1 ")));
+    assert!(received.iter().any(|r| r.contains("scan.pdf")));
+    assert!(received.iter().any(|r| r.contains("page.pdf")));
+    Ok(())
+}
+
+#[test]
+fn watch_scan_existing_duplicates() -> Result<()> {
+    let base = Path::new("target/test_watch_scan_existing_duplicates");
+    let _ = fs::remove_dir_all(base);
+    let watched = base.join("inbox");
+    fs::create_dir_all(&watched)?;
+    fs::write(watched.join("scan.pdf"), "a scanned file")?;
+
+    let (url, requests) = stub_server(|request| {
+        if request.starts_with("GET /api/v1/open/checkfile/") {
+            r#"{"exists":true,"items":[],"file":null}"#.to_string()
+        } else {
+            basic_result_json(true, "Files submitted.")
+        }
+    })?;
+    let watch = |args: &[&str]| -> Result<Vec<String>> {
+        let mut child = mk_cmd()?
+            .args(["-d", &url, "watch", "--source", "src1", "--scan-existing"])
+            .args(["--delay", "1", "--stable-secs", "0", "--delete"])
+            .arg("--queue-file")
+            .arg(base.join("queue.json"))
+            .args(args)
+            .arg(&watched)
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // The existing files are handled before the watcher is ready
+        let stderr = BufReader::new(child.stderr.take().unwrap());
+        for line in stderr.lines() {
+            if line?.starts_with("Press Ctrl-C") {
+                break;
+            }
+        }
+        child.kill()?;
+        child.wait()?;
+        Ok(requests.try_iter().collect())
+    };
+
+    // a file already in Docspell is skipped, without deleting it
+    let received = watch(&[])?;
+    assert!(received.iter().any(|r| r.contains("/checkfile/")));
+    assert!(!received.iter().any(|r| r.starts_with("POST ")));
+    assert!(watched.join("scan.pdf").exists());
+
+    // with --allow-dupes it is uploaded without checking
+    let received = watch(&["--allow-dupes"])?;
+    assert!(!received.iter().any(|r| r.contains("/checkfile/")));
+    assert!(received.iter().any(|r| r.starts_with("POST ")));
+    assert!(!watched.join("scan.pdf").exists());
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn upload_on_error_move() -> Result<()> {
+    let base = Path::new("target/test_upload_on_error_move");
+    let _ = fs::remove_dir_all(base);
+    let inbox = base.join("inbox");
+    fs::create_dir_all(inbox.join("sub"))?;
+    fs::write(inbox.join("sub").join("scan.pdf"), "a scanned file")?;
+
+    let (url, requests) = stub_upload_server(basic_result(false, "Unsupported file"))?;
+    let failed = base.join("failed");
+    let assert = mk_cmd()?
+        .args(["-d", &url, "upload", "--source", "src1", "--traverse"])
+        .arg("--on-error")
+        .arg(format!("move:{}", failed.display()))
+        .arg(&inbox)
+        .assert();
+    assert
+        .success()
+        .stdout(basic_result_json(false, "Uploaded 0, failed 1"));
+
+    assert!(requests.try_iter().any(|r| r.starts_with("POST ")));
+    assert!(!inbox.join("sub").exists());
+    assert!(failed.join("sub").join("scan.pdf").exists());
+    let report = fs::read_to_string(failed.join("sub").join("scan.pdf.error.txt"))?;
+    assert!(report.contains("Unsupported file"));
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn upload_on_error_unauthorized() -> Result<()> {
+    let base = Path::new("target/test_upload_on_error_unauthorized");
+    let _ = fs::remove_dir_all(base);
+    let inbox = base.join("inbox");
+    fs::create_dir_all(&inbox)?;
+    fs::write(inbox.join("scan.pdf"), "a scanned file")?;
+
+    let (url, _requests) = stub_status_server(|request| {
+        if request.starts_with("GET /api/v1/open/checkfile/") {
+            (
+                200,
+                r#"{"exists":false,"items":[],"file":null}"#.to_string(),
+            )
+        } else {
+            (401, "{}".to_string())
+        }
+    })?;
+    let failed = base.join("failed");
+    let assert = mk_cmd()?
+        .args(["-d", &url, "upload", "--source", "src1", "--traverse"])
+        .arg("--on-error")
+        .arg(format!("move:{}", failed.display()))
+        .arg(&inbox)
+        .assert();
+    assert.failure();
+
+    assert!(inbox.join("scan.pdf").exists());
+    assert!(!failed.exists());
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn upload_error_report_without_file() -> Result<()> {
+    let base = Path::new("target/test_upload_error_report_without_file");
+    let _ = fs::remove_dir_all(base);
+    fs::create_dir_all(base)?;
+    fs::write(base.join("scan.pdf"), "a scanned file")?;
+    fs::write(base.join("scan.pdf.error.txt"), "Error: Unsupported file")?;
+    fs::write(base.join("build.error.txt"), "a file of the user")?;
+
+    let (url, requests) = stub_upload_server(basic_result(true, "ok"))?;
+    let assert = mk_cmd()?
+        .args(["-d", &url, "upload", "--source", "src1", "--traverse"])
+        .arg(base)
+        .assert();
+    assert
+        .success()
+        .stdout(basic_result_json(true, "Uploaded 2"));
+
+    let uploads: Vec<String> = requests
+        .try_iter()
+        .filter(|r| r.starts_with("POST "))
+        .collect();
+    assert!(uploads.iter().any(|r| r.contains("build.error.txt")));
+    assert!(!uploads.iter().any(|r| r.contains("scan.pdf.error.txt")));
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn upload_parallel_same_contents() -> Result<()> {
+    let base = Path::new("target/test_upload_parallel_same_contents");
+    let _ = fs::remove_dir_all(base);
+    fs::create_dir_all(base)?;
+    for name in ["a.pdf", "b.pdf", "c.pdf", "d.pdf"] {
+        fs::write(base.join(name), "the same scanned file")?;
+    }
+
+    let (url, requests) = stub_upload_server(basic_result(true, "ok"))?;
+    let assert = mk_cmd()?
+        .args(["-d", &url, "upload", "--source", "src1", "--traverse"])
+        .args(["--parallel", "4"])
+        .arg(base)
+        .assert();
+    assert
+        .success()
+        .stdout(basic_result_json(true, "Uploaded 1"));
+
+    let uploads = requests
+        .try_iter()
+        .filter(|r| r.starts_with("POST "))
+        .count();
+    assert_eq!(uploads, 1);
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn upload_sidecar_rejected() -> Result<()> {
+    let base = Path::new("target/test_upload_sidecar_rejected");
+    let _ = fs::remove_dir_all(base);
+    fs::create_dir_all(base)?;
+    for name in ["a.pdf", "b.pdf", "c.pdf"] {
+        fs::write(base.join(name), format!("scanned file {}", name))?;
+    }
+    fs::write(base.join("a.pdf.json"), r#"{"tags": ["invoice"]}"#)?;
+    fs::write(base.join("b.pdf.json"), r#"{"tags": ["invoice"]}"#)?;
+
+    let rejected = serde_json::to_string(&basic_result(false, "Unsupported file"))?;
+    let accepted = serde_json::to_string(&basic_result(true, "Files submitted."))?;
+    let (url, _requests) = stub_server(move |request| {
+        if request.starts_with("GET /api/v1/open/checkfile/") {
+            r#"{"exists":false,"items":[],"file":null}"#.to_string()
+        } else if request.contains("filename=\"a.pdf\"") {
+            rejected.clone()
+        } else {
+            accepted.clone()
+        }
+    })?;
+    let assert = mk_cmd()?
+        .args(["-d", &url, "upload", "--source", "src1"])
+        .args(["a.pdf", "b.pdf", "c.pdf"].map(|n| base.join(n)))
+        .assert();
+    assert
+        .success()
+        .stdout(basic_result_json(false, "Uploaded 2, failed 1"));
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+/// Writes an exported item with a single file below `base`.
+fn write_exported_item(base: &Path, id: &str, name: &str, file: &str) -> Result<PathBuf> {
+    let dir = base.join("items").join(&id[0..2]).join(id);
+    fs::create_dir_all(dir.join("files"))?;
+    fs::write(dir.join("files").join(file), format!("content of {}", name))?;
+    fs::write(
+        dir.join("metadata.json"),
+        serde_json::to_vec(&item_json(id, name))?,
+    )?;
+    Ok(dir)
+}
+
+/// The metadata of an item without attachments.
+fn item_json(id: &str, name: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": id, "name": name, "state": "confirmed", "date": 1706659200000i64,
+        "dueDate": null, "source": "webapp", "direction": "incoming",
+        "corrOrg": {"id": "o1", "name": "ACME"}, "corrPerson": null,
+        "concPerson": null, "concEquipment": null,
+        "folder": {"id": "f0", "name": "Finance"},
+        "attachments": [], "customfields": [], "notes": "Paid",
+        "tags": [{"id": "t0", "name": "invoice", "category": "doctype", "created": 0}],
+        "highlighting": [], "related": []
+    })
+}
+
+/// Starts a server for exporting. Searches list the first item, or
+/// both items once `second` is set.
+fn stub_export_server(second: Arc<AtomicBool>) -> Result<(String, mpsc::Receiver<String>)> {
+    stub_server(move |req| {
+        let items = if !req.starts_with("GET /api/v1/sec/item/search?") {
+            return r#"{"name":"related","items":[]}"#.into();
+        } else if req.contains("offset=0") && !req.contains("searchMode=trashed") {
+            if second.load(Ordering::SeqCst) {
+                vec![item_json(ITEM_ID1, "Bill"), item_json(ITEM_ID2, "Old")]
+            } else {
+                vec![item_json(ITEM_ID1, "Bill")]
+            }
+        } else {
+            vec![]
+        };
+        serde_json::json!({"groups": [{"name": "all", "items": items}]}).to_string()
+    })
+}
+
+/// The directory of an exported item.
+fn exported_item(base: &Path, id: &str) -> PathBuf {
+    base.join("items").join(&id[0..2]).join(id)
+}
+
+#[test]
+fn export_since() -> Result<()> {
+    let base = Path::new("target/test_export_since");
+    let _ = fs::remove_dir_all(base);
+    let second = Arc::new(AtomicBool::new(false));
+    let (url, requests) = stub_export_server(second.clone())?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+    let token = format!("{}-test", now.unwrap().as_millis());
+    let export = |args: &[&str]| -> Result<assert_cmd::assert::Assert> {
+        Ok(mk_cmd()?
+            .args(["-d", &url, "--session", &token, "export", "--target"])
+            .arg(base)
+            .args(args)
+            .assert())
+    };
+
+    export(&["--all"])?.success();
+    assert!(exported_item(base, ITEM_ID1).join("metadata.json").exists());
+    requests.try_iter().for_each(drop);
+    mk_cmd()?
+        .args(["export", "verify", "--target"])
+        .arg(base)
+        .assert()
+        .success();
+
+    second.store(true, Ordering::SeqCst);
+    export(&["--since", "last"])?.success();
+    // the query is restricted to items created since the last run,
+    // and all ids are fetched to find removed items
+    assert!(exported_item(base, ITEM_ID1).exists());
+    assert!(exported_item(base, ITEM_ID2).join("metadata.json").exists());
+    let searches: Vec<String> = requests
+        .try_iter()
+        .filter(|r| r.starts_with("GET /api/v1/sec/item/search?"))
+        .collect();
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let restricted: Vec<&String> = searches
+        .iter()
+        .filter(|r| r.contains("created") && r.contains(&today))
+        .collect();
+    assert_eq!(restricted.len(), 1);
+    assert!(restricted[0].contains("withDetails=true"));
+    assert!(searches
+        .iter()
+        .any(|r| !r.contains("created") && r.contains("withDetails=false")));
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn export_since_links() -> Result<()> {
+    let base = Path::new("target/test_export_since_links");
+    let _ = fs::remove_dir_all(base);
+    let second = Arc::new(AtomicBool::new(false));
+    let (url, _requests) = stub_export_server(second.clone())?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+    let token = format!("{}-test", now.unwrap().as_millis());
+    let export = |args: &[&str]| -> Result<assert_cmd::assert::Assert> {
+        Ok(mk_cmd()?
+            .args([
+                "-d",
+                &url,
+                "--session",
+                &token,
+                "export",
+                "--tag-links",
+                "--target",
+            ])
+            .arg(base)
+            .args(args)
+            .assert())
+    };
+    let links = || -> Result<Vec<String>> {
+        let mut names: Vec<String> = fs::read_dir(base.join("by_tag").join("invoice"))?
+            .map(|e| e.map(|e| e.file_name().to_string_lossy().to_string()))
+            .collect::<std::io::Result<_>>()?;
+        names.sort();
+        Ok(names)
+    };
+
+    export(&["--all"])?.success();
+    let before = links()?;
+    assert_eq!(before.len(), 1);
+
+    // the unchanged item keeps its link, the new one gets one
+    second.store(true, Ordering::SeqCst);
+    export(&["--since", "2024-01-01"])?.success();
+    export(&["--since", "last", "--prune"])?.failure();
+    let after = links()?;
+    assert_eq!(after.len(), 2);
+    assert!(after.contains(&before[0]));
+    assert!(exported_item(base, ITEM_ID1).exists());
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn import_export_dir() -> Result<()> {
+    const NEW_ID: &str = "7xVd3ZQhTnR-B9cLp5mWq2e-Kf8sYhUaGj4-Nt6rEw1zXo3";
+    let base = Path::new("target/test_import_export_dir");
+    let _ = fs::remove_dir_all(base);
+    write_exported_item(base, ITEM_ID1, "Electricity bill", "bill.pdf")?;
+    let removed = write_exported_item(base, ITEM_ID2, "Old letter", "letter.pdf")?;
+    fs::write(removed.join("removed.txt"), "deleted")?;
+
+    let uploaded = Arc::new(AtomicBool::new(false));
+    let (url, requests) = stub_server(move |req| {
+        let ok = basic_result_json(true, "ok");
+        if req.starts_with("GET /api/v1/sec/checkfile/") {
+            if uploaded.load(Ordering::SeqCst) {
+                format!(
+                    r#"{{"exists":true,"file":null,"items":[{{"id":"{}","name":"bill",
+                        "direction":"incoming","state":"created","created":0}}]}}"#,
+                    NEW_ID
+                )
+            } else {
+                r#"{"exists":false,"items":[],"file":null}"#.into()
+            }
+        } else if req.starts_with("POST /api/v1/sec/upload/item ") {
+            uploaded.store(true, Ordering::SeqCst);
+            ok
+        } else if req.starts_with("GET /api/v1/sec/tag") {
+            r#"{"count":0,"items":[]}"#.into()
+        } else if req.starts_with("GET /api/v1/sec/folder") {
+            r#"{"items":[{"id":"f1","name":"Finance"}]}"#.into()
+        } else if req.starts_with("GET /api/v1/sec/") {
+            r#"{"items":[]}"#.into()
+        } else {
+            ok
+        }
+    })?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+    let token = format!("{}-test", now.unwrap().as_millis());
+
+    let run = || -> Result<assert_cmd::assert::Assert> {
+        Ok(mk_cmd()?
+            .args(["-d", &url, "--session", &token, "import"])
+            .arg(base)
+            .assert())
+    };
+    // a dry run doesn't contact the server
+    mk_cmd()?
+        .args(["-d", &url, "--session", &token, "import", "--dry-run"])
+        .arg(base)
+        .assert()
+        .success()
+        .stdout(basic_result_json(
+            true,
+            "Imported 1 items, updated 0, skipped 1",
+        ));
+    assert_eq!(requests.try_iter().count(), 0);
+
+    run()?.success().stdout(basic_result_json(
+        true,
+        "Imported 1 items, updated 0, skipped 1",
+    ));
+    let received: Vec<String> = requests.try_iter().collect();
+    let find = |prefix: &str| received.iter().find(|r| r.starts_with(prefix));
+
+    let upload = find("POST /api/v1/sec/upload/item ").expect("upload request");
+    assert!(upload.contains("bill.pdf"));
+    assert!(!received.iter().any(|r| r.contains("letter.pdf")));
+    let item_url = format!("PUT /api/v1/sec/item/{}", NEW_ID);
+    assert!(find(&format!("{}/name ", item_url))
+        .unwrap()
+        .contains("Electricity bill"));
+    assert!(find(&format!("{}/date ", item_url))
+        .unwrap()
+        .contains("1706659200000"));
+    assert!(find(&format!("{}/notes ", item_url))
+        .unwrap()
+        .contains("Paid"));
+    assert!(find(&format!("{}/folder ", item_url))
+        .unwrap()
+        .contains("f1"));
+    assert!(find("POST /api/v1/sec/tag ").unwrap().contains("doctype"));
+    assert!(find(&format!("{}/tags ", item_url))
+        .unwrap()
+        .contains("invoice"));
+    // the organization doesn't exist on the server
+    assert!(find(&format!("{}/corrOrg ", item_url)).is_none());
+
+    // the item is in Docspell now and only its metadata is applied
+    // when importing again
+    run()?.success().stdout(basic_result_json(
+        true,
+        "Imported 0 items, updated 1, skipped 1",
+    ));
+    let received: Vec<String> = requests.try_iter().collect();
+    assert!(!received
+        .iter()
+        .any(|r| r.starts_with("POST /api/v1/sec/upload/item ")));
+    assert!(received
+        .iter()
+        .any(|r| r.starts_with(&format!("{}/name ", item_url))));
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn import_partially_existing() -> Result<()> {
+    const OTHER_ID: &str = "5pLq8WzRt3K-Hd2nFv7XcE4-Jb9sMa6YwUq-Gk1rTe3ZnP8";
+    let base = Path::new("target/test_import_partially_existing");
+    let _ = fs::remove_dir_all(base);
+    let dir = write_exported_item(base, ITEM_ID1, "Electricity bill", "bill.pdf")?;
+    fs::write(dir.join("files").join("page2.pdf"), "another page")?;
+
+    // only the first file is in another item on the server
+    let checks = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let (url, requests) = stub_server(move |req| {
+        if req.starts_with("GET /api/v1/sec/checkfile/") {
+            if checks.fetch_add(1, Ordering::SeqCst) == 0 {
+                format!(
+                    r#"{{"exists":true,"file":null,"items":[{{"id":"{}","name":"scan",
+                        "direction":"incoming","state":"created","created":0}}]}}"#,
+                    OTHER_ID
+                )
+            } else {
+                r#"{"exists":false,"items":[],"file":null}"#.into()
+            }
+        } else {
+            basic_result_json(false, "Unsupported file")
+        }
+    })?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+    let token = format!("{}-test", now.unwrap().as_millis());
+
+    mk_cmd()?
+        .args(["-d", &url, "--session", &token, "import"])
+        .arg(base)
+        .assert()
+        .failure();
+    let received: Vec<String> = requests.try_iter().collect();
+    assert!(received
+        .iter()
+        .any(|r| r.starts_with("POST /api/v1/sec/upload/item ")));
+    assert!(!received.iter().any(|r| r.contains(OTHER_ID)));
+    fs::remove_dir_all(base)?;
+    Ok(())
+}
+
+#[test]
+fn import_rejected() -> Result<()> {
+    let base = Path::new("target/test_import_rejected");
+    let _ = fs::remove_dir_all(base);
+    write_exported_item(base, ITEM_ID1, "Electricity bill", "bill.pdf")?;
+    let (url, _requests) = stub_server(|req| {
+        if req.starts_with("GET /api/v1/sec/checkfile/") {
+            r#"{"exists":false,"items":[],"file":null}"#.into()
+        } else {
+            basic_result_json(false, "Unsupported file")
+        }
+    })?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+    let token = format!("{}-test", now.unwrap().as_millis());
+
+    // fails right away instead of waiting for the item
+    let start = std::time::Instant::now();
+    mk_cmd()?
+        .args(["-d", &url, "--session", &token, "import", "--timeout", "60"])
+        .arg(base)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Unsupported file"));
+    assert!(start.elapsed() < Duration::from_secs(30));
+    fs::remove_dir_all(base)?;
+    Ok(())
+}